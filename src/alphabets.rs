@@ -1,3 +1,4 @@
+use super::error::Error;
 use std::collections::HashMap;
 
 const ALPHABETS: [(&'static str, &'static str); 22] = [
@@ -25,13 +26,23 @@ const ALPHABETS: [(&'static str, &'static str); 22] = [
   ("colemak-right-hand", "neioluymjhk"),
 ];
 
-pub struct Alphabet<'a> {
-  letters: &'a str,
+/// Letters to build the hints with.
+pub struct Alphabet {
+  letters: String,
 }
 
-impl<'a> Alphabet<'a> {
-  fn new(letters: &'a str) -> Alphabet {
-    Alphabet { letters: letters }
+impl Alphabet {
+  pub fn new(letters: &str) -> Alphabet {
+    Alphabet {
+      letters: letters.to_string(),
+    }
+  }
+
+  /// Tells if the letter, or its lowercase version, is used in the hints.
+  pub fn contains(&self, letter: char) -> bool {
+    letter
+      .to_lowercase()
+      .all(|letter| self.letters.contains(letter))
   }
 
   pub fn hints(&self, matches: usize) -> Vec<String> {
@@ -44,11 +55,11 @@ impl<'a> Alphabet<'a> {
       if expansion.len() + expanded.len() >= matches {
         break;
       }
-      if expansion.len() == 0 {
-        break;
-      }
 
-      let prefix = expansion.pop().expect("Ouch!");
+      let prefix = match expansion.pop() {
+        Some(prefix) => prefix,
+        None => break,
+      };
       let sub_expansion: Vec<String> = letters
         .iter()
         .take(matches - expansion.len() - expanded.len())
@@ -68,14 +79,13 @@ impl<'a> Alphabet<'a> {
   }
 }
 
-pub fn get_alphabet(alphabet_name: &str) -> Alphabet {
-  let alphabets: HashMap<&str, &str> = ALPHABETS.iter().cloned().collect();
-
-  alphabets
-    .get(alphabet_name)
-    .expect(format!("Unknown alphabet: {}", alphabet_name).as_str()); // FIXME
+pub fn get_alphabet(alphabet_name: &str) -> Result<Alphabet, Error> {
+  let alphabets: HashMap<&str, &'static str> = ALPHABETS.iter().cloned().collect();
 
-  Alphabet::new(alphabets[alphabet_name])
+  match alphabets.get(alphabet_name) {
+    Some(letters) => Ok(Alphabet::new(letters)),
+    None => Err(Error::UnknownAlphabet(alphabet_name.to_string())),
+  }
 }
 
 #[cfg(test)]
@@ -109,4 +119,19 @@ mod tests {
     let hints = alphabet.hints(8);
     assert_eq!(hints, ["aa", "ab", "ba", "bb"]);
   }
+
+  #[test]
+  fn contains_letters() {
+    let alphabet = Alphabet::new("abcd");
+
+    assert!(alphabet.contains('a'));
+    assert!(alphabet.contains('B'));
+    assert!(!alphabet.contains('y'));
+  }
+
+  #[test]
+  fn unknown_alphabet() {
+    assert!(get_alphabet("qwerty").is_ok());
+    assert!(get_alphabet("foo").is_err());
+  }
 }
@@ -0,0 +1,403 @@
+use std::str::Chars;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+const TAB_WIDTH: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Color {
+  Default,
+  Indexed(u8),
+  Rgb(u8, u8, u8),
+}
+
+/// Graphic attributes set by SGR escape sequences.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Style {
+  pub foreground: Color,
+  pub background: Color,
+  pub bold: bool,
+  pub italic: bool,
+  pub underline: bool,
+  pub reverse: bool,
+}
+
+impl Default for Style {
+  fn default() -> Style {
+    Style {
+      foreground: Color::Default,
+      background: Color::Default,
+      bold: false,
+      italic: false,
+      underline: false,
+      reverse: false,
+    }
+  }
+}
+
+/// A grapheme cluster of the clean text, with the screen columns where it is drawn.
+#[derive(Clone, Debug)]
+pub struct Cell {
+  pub start: usize,
+  pub end: usize,
+  pub x: usize,
+  pub width: usize,
+  pub style: Style,
+}
+
+/// A captured line: the text without escape sequences and the cells to draw it, and
+/// whether the terminal wrapped it to the next one.
+#[derive(Clone, Debug, Default)]
+pub struct Line {
+  pub text: String,
+  pub cells: Vec<Cell>,
+  pub wrapped: bool,
+}
+
+impl Line {
+  /// Screen column of the grapheme cluster starting at the given byte of the text. Bytes
+  /// inside a cluster map to the next one, and the end of the text to the line width.
+  pub fn column(&self, index: usize) -> usize {
+    match self.cells.binary_search_by_key(&index, |cell| cell.start) {
+      Ok(position) => self.cells[position].x,
+      Err(position) => self.cells.get(position).map_or(self.width(), |cell| cell.x),
+    }
+  }
+
+  /// Number of screen columns used by the line.
+  pub fn width(&self) -> usize {
+    self.cells.last().map_or(0, |cell| cell.x + cell.width)
+  }
+
+  pub fn cell_text(&self, cell: &Cell) -> &str {
+    &self.text[cell.start..cell.end]
+  }
+
+  /// Splits a line captured joined, with `capture-pane -J`, back into the rows of a pane
+  /// of the given width, all of them but the last one wrapped. A wide character that
+  /// doesn't fit at the end of a row starts the next one, like in the terminal.
+  pub fn rows(&self, width: usize) -> Vec<Line> {
+    let mut rows = vec![Line::default()];
+    let mut x = 0;
+
+    for cell in self.cells.iter() {
+      if x > 0 && x + cell.width > width {
+        rows.last_mut().unwrap().wrapped = true;
+        rows.push(Line::default());
+        x = 0;
+      }
+
+      let row = rows.last_mut().unwrap();
+      let start = row.text.len();
+
+      row.text.push_str(self.cell_text(cell));
+      row.cells.push(Cell {
+        start: start,
+        end: row.text.len(),
+        x: x,
+        ..cell.clone()
+      });
+
+      x += cell.width;
+    }
+
+    rows
+  }
+
+  /// Splits the text in grapheme clusters, drawn with the style of their first character
+  /// and as wide as the terminal draws them. Tabs move to the next tab stop.
+  fn layout(&mut self, styles: &[Style]) {
+    let mut x = 0;
+    let mut index = 0;
+
+    for (start, grapheme) in self.text.grapheme_indices(true) {
+      let width = if grapheme == "\t" {
+        TAB_WIDTH - x % TAB_WIDTH
+      } else {
+        grapheme.width()
+      };
+
+      self.cells.push(Cell {
+        start: start,
+        end: start + grapheme.len(),
+        x: x,
+        width: width,
+        style: styles[index],
+      });
+
+      x += width;
+      index += grapheme.chars().count();
+    }
+  }
+}
+
+/// Parses the output of `tmux capture-pane -e`. Styles carry over from one line to the
+/// next like they do in the terminal, SGR sequences update them and any other escape
+/// sequence or control character is dropped.
+pub fn parse(output: &str) -> Vec<Line> {
+  let mut style = Style::default();
+
+  output
+    .split('\n')
+    .map(|raw| {
+      let mut line = Line::default();
+      let mut styles = Vec::new();
+      let mut chars = raw.chars();
+
+      while let Some(c) = chars.next() {
+        match c {
+          '\x1b' => {
+            if let Some(params) = escape(&mut chars) {
+              apply(&mut style, &params);
+            }
+          }
+          c if c.is_control() && c != '\t' => {}
+          c => {
+            line.text.push(c);
+            styles.push(style);
+          }
+        }
+      }
+
+      line.layout(&styles);
+      line
+    })
+    .collect()
+}
+
+/// Consumes an escape sequence, returning the parameters when it is a SGR one.
+fn escape(chars: &mut Chars) -> Option<String> {
+  match chars.next()? {
+    // CSI: parameters and intermediate bytes up to a final byte
+    '[' => {
+      let mut params = String::new();
+
+      for c in chars {
+        match c {
+          '\x40'..='\x7e' => return if c == 'm' { Some(params) } else { None },
+          c => params.push(c),
+        }
+      }
+
+      None
+    }
+    // OSC, DCS and friends: strings terminated by BEL or ST
+    ']' | 'P' | 'X' | '^' | '_' => {
+      let mut previous = ' ';
+
+      for c in chars {
+        if c == '\x07' || (previous == '\x1b' && c == '\\') {
+          break;
+        }
+
+        previous = c;
+      }
+
+      None
+    }
+    // Intermediate bytes followed by a final one, like charset selection
+    '\x20'..='\x2f' => {
+      for c in chars {
+        if let '\x30'..='\x7e' = c {
+          break;
+        }
+      }
+
+      None
+    }
+    _ => None,
+  }
+}
+
+/// Applies SGR parameters to the style. Parameters are separated by `;`, and `:`
+/// separates the sub-parameters of a single one, like `4:3` or `38:2::r:g:b`. Unknown
+/// attributes are ignored.
+fn apply(style: &mut Style, params: &str) {
+  let mut params = params.split(';');
+
+  while let Some(param) = params.next() {
+    let mut parts = param.split(':');
+    let code = number(parts.next().unwrap_or(""));
+    let subs = parts.map(number).collect::<Vec<_>>();
+
+    match code {
+      0 => *style = Style::default(),
+      1 => style.bold = true,
+      3 => style.italic = true,
+      // Styled underlines like `4:3` are still underlines, and `4:0` is none
+      4 => style.underline = subs.first() != Some(&0),
+      7 => style.reverse = true,
+      22 => style.bold = false,
+      23 => style.italic = false,
+      24 => style.underline = false,
+      27 => style.reverse = false,
+      30..=37 => style.foreground = Color::Indexed(code - 30),
+      38 => style.foreground = extended(&subs, &mut params).unwrap_or(style.foreground),
+      39 => style.foreground = Color::Default,
+      40..=47 => style.background = Color::Indexed(code - 40),
+      48 => style.background = extended(&subs, &mut params).unwrap_or(style.background),
+      49 => style.background = Color::Default,
+      90..=97 => style.foreground = Color::Indexed(code - 90 + 8),
+      100..=107 => style.background = Color::Indexed(code - 100 + 8),
+      _ => {}
+    }
+  }
+}
+
+/// A numeric SGR parameter, where an empty or invalid one counts as 0.
+fn number(param: &str) -> u8 {
+  param.parse::<u8>().unwrap_or(0)
+}
+
+/// Reads the 256 colors (`5;n`) or truecolor (`2;r;g;b`) arguments of codes 38 and 48,
+/// from the next parameters, or from the sub-parameters of the `5:n` and `2::r:g:b`
+/// forms, where the color space before the components may be left out.
+fn extended<'a, I: Iterator<Item = &'a str>>(subs: &[u8], params: &mut I) -> Option<Color> {
+  if subs.is_empty() {
+    let mut next = || params.next().map(number);
+
+    return match next()? {
+      5 => Some(Color::Indexed(next()?)),
+      2 => Some(Color::Rgb(next()?, next()?, next()?)),
+      _ => None,
+    };
+  }
+
+  match *subs {
+    [5, index, ..] => Some(Color::Indexed(index)),
+    [2, r, g, b] => Some(Color::Rgb(r, g, b)),
+    [2, _, r, g, b, ..] => Some(Color::Rgb(r, g, b)),
+    _ => None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parse_clean_text() {
+    let lines = parse("lorem \x1b[32m/var/log\x1b[m ipsum\n\x1b[01;33mcommit\x1b[m: fd70b5695");
+
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0].text, "lorem /var/log ipsum");
+    assert_eq!(lines[0].width(), 20);
+    assert_eq!(lines[1].text, "commit: fd70b5695");
+  }
+
+  #[test]
+  fn parse_styles() {
+    let lines = parse("a\x1b[1;31mb\x1b[22;44mc\x1b[0md\x1b[38;5;208me\x1b[48;2;1;2;3mf");
+    let styles = lines[0]
+      .cells
+      .iter()
+      .map(|cell| cell.style)
+      .collect::<Vec<_>>();
+
+    assert_eq!(styles[0], Style::default());
+    assert_eq!(styles[1].bold, true);
+    assert_eq!(styles[1].foreground, Color::Indexed(1));
+    assert_eq!(styles[2].bold, false);
+    assert_eq!(styles[2].background, Color::Indexed(4));
+    assert_eq!(styles[3], Style::default());
+    assert_eq!(styles[4].foreground, Color::Indexed(208));
+    assert_eq!(styles[5].background, Color::Rgb(1, 2, 3));
+  }
+
+  #[test]
+  fn parse_sub_parameters() {
+    let lines = parse("\x1b[4:3ma\x1b[1;4:0mb\x1b[38:2::1:2:3mc\x1b[48:5:208;3md\x1b[38:2:4:5:6me");
+    let styles = lines[0]
+      .cells
+      .iter()
+      .map(|cell| cell.style)
+      .collect::<Vec<_>>();
+
+    assert_eq!(styles[0].underline, true);
+    assert_eq!(styles[0].italic, false);
+    assert_eq!(styles[1].bold, true);
+    assert_eq!(styles[1].underline, false);
+    assert_eq!(styles[2].foreground, Color::Rgb(1, 2, 3));
+    assert_eq!(styles[2].bold, true);
+    assert_eq!(styles[3].background, Color::Indexed(208));
+    assert_eq!(styles[3].italic, true);
+    assert_eq!(styles[4].foreground, Color::Rgb(4, 5, 6));
+  }
+
+  #[test]
+  fn parse_style_across_lines() {
+    let lines = parse("\x1b[34mfoo\nbar\x1b[m");
+
+    assert_eq!(lines[1].cells[0].style.foreground, Color::Indexed(4));
+  }
+
+  #[test]
+  fn parse_other_sequences() {
+    let lines = parse("a\x1b[2Kb\x1b]8;;https://foo.bar\x1b\\c\x1b]0;title\x07d\x1b(Be\x07f");
+
+    assert_eq!(lines[0].text, "abcdef");
+  }
+
+  #[test]
+  fn parse_columns() {
+    let lines = parse("\x1b[32mé\x1b[mlorem");
+
+    assert_eq!(lines[0].column(0), 0);
+    assert_eq!(lines[0].column(2), 1);
+    assert_eq!(lines[0].column(lines[0].text.len()), 6);
+  }
+
+  #[test]
+  fn parse_wide_columns() {
+    let lines = parse("日本 lorem\n😀 lorem");
+
+    assert_eq!(lines[0].cells[1].x, 2);
+    assert_eq!(lines[0].cells[1].width, 2);
+    assert_eq!(lines[0].column("日本 ".len()), 5);
+    assert_eq!(lines[0].width(), 10);
+    assert_eq!(lines[1].column("😀 ".len()), 3);
+  }
+
+  #[test]
+  fn parse_combining_columns() {
+    let lines = parse("e\u{301}\u{302} lorem");
+
+    assert_eq!(lines[0].cells.len(), 7);
+    assert_eq!(lines[0].cell_text(&lines[0].cells[0]), "e\u{301}\u{302}");
+    assert_eq!(lines[0].column("e\u{301}\u{302} ".len()), 2);
+    assert_eq!(lines[0].column(1), 1);
+  }
+
+  #[test]
+  fn split_rows() {
+    let lines = parse("lorem ipsum dolor\nlorem 日本\n\nlorem");
+    let rows = lines[0].rows(6);
+
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[0].text, "lorem ");
+    assert_eq!(rows[1].text, "ipsum ");
+    assert_eq!(rows[2].text, "dolor");
+    assert_eq!(rows[1].cells[0].x, 0);
+    assert_eq!(rows[1].cells[1].start, 1);
+    assert!(rows[0].wrapped && rows[1].wrapped && !rows[2].wrapped);
+
+    // The wide character doesn't fit in the last column
+    let rows = lines[1].rows(7);
+
+    assert_eq!(rows[0].text, "lorem ");
+    assert_eq!(rows[1].text, "日本");
+    assert_eq!(rows[1].cells[1].x, 2);
+
+    assert_eq!(lines[2].rows(6).len(), 1);
+    assert!(!lines[3].rows(5)[0].wrapped);
+  }
+
+  #[test]
+  fn parse_tab_columns() {
+    let lines = parse("a\tlorem\t\tb");
+
+    assert_eq!(lines[0].cells[1].width, 7);
+    assert_eq!(lines[0].column(2), 8);
+    assert_eq!(lines[0].column("a\tlorem\t\t".len()), 24);
+  }
+}
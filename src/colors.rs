@@ -1,3 +1,5 @@
+use super::capture;
+use super::error::Error;
 use rustbox::Color;
 use std::collections::HashMap;
 
@@ -13,14 +15,71 @@ const COLORS: [(&'static str, Color); 9] = [
   ("default", Color::Default),
 ];
 
-pub fn get_color(color_name: &str) -> Color {
+pub fn get_color(color_name: &str) -> Result<Color, Error> {
   let available_colors: HashMap<&str, Color> = COLORS.iter().cloned().collect();
 
-  available_colors
-    .get(color_name)
-    .expect(format!("Unknown color: {}", color_name).as_str()); // FIXME
+  match available_colors.get(color_name) {
+    Some(color) => Ok(*color),
+    None => Err(Error::UnknownColor(color_name.to_string())),
+  }
+}
+
+/// Converts a captured color to the 256 colors palette. Termbox can't output the terminal
+/// default colors in that mode, so those become the fallback color.
+pub fn get_capture_color(color: capture::Color, fallback: Color) -> Color {
+  match color {
+    capture::Color::Default => fallback,
+    capture::Color::Indexed(index) => Color::Byte(index as u16),
+    capture::Color::Rgb(r, g, b) => Color::Byte(closest_256color(r, g, b)),
+  }
+}
+
+/// Tells if the captured color can be drawn with the terminal colors: the default one,
+/// the 8 basic ones, and for the foreground, the bright ones.
+pub fn is_basic_color(color: capture::Color, foreground: bool) -> bool {
+  match color {
+    capture::Color::Default => true,
+    capture::Color::Indexed(index) => index < 8 || (foreground && index < 16),
+    capture::Color::Rgb(..) => false,
+  }
+}
 
-  available_colors[&color_name]
+/// Converts a captured color to the terminal colors, keeping the default one. Bright
+/// colors become the basic ones, and tell to draw them in bold, which terminals show
+/// bright.
+pub fn get_basic_color(color: capture::Color) -> (Color, bool) {
+  match color {
+    capture::Color::Indexed(index) if index < 16 => (COLORS[index as usize % 8].1, index >= 8),
+    _ => (Color::Default, false),
+  }
+}
+
+/// Finds the closest color of the 6x6x6 cube or the grayscale ramp of the 256 colors
+/// palette.
+fn closest_256color(r: u8, g: u8, b: u8) -> u16 {
+  let levels = [0, 95, 135, 175, 215, 255];
+  let level = |value: u8| {
+    (0..levels.len())
+      .min_by_key(|index| (levels[*index] - value as i32).abs())
+      .unwrap()
+  };
+  let distance = |(x, y, z): (i32, i32, i32)| {
+    (x - r as i32).pow(2) + (y - g as i32).pow(2) + (z - b as i32).pow(2)
+  };
+
+  let (ri, gi, bi) = (level(r), level(g), level(b));
+  let cube = (levels[ri], levels[gi], levels[bi]);
+
+  let average = (r as i32 + g as i32 + b as i32) / 3;
+  let gray_index = ((average - 8).max(0) / 10).min(23);
+  let gray_level = 8 + gray_index * 10;
+  let gray = (gray_level, gray_level, gray_level);
+
+  if distance(gray) < distance(cube) {
+    232 + gray_index as u16
+  } else {
+    16 + (36 * ri + 6 * gi + bi) as u16
+  }
 }
 
 #[cfg(test)]
@@ -29,6 +88,51 @@ mod tests {
 
   #[test]
   fn match_color() {
-    assert_eq!(get_color("green"), Color::Green);
+    assert_eq!(get_color("green").unwrap(), Color::Green);
+    assert!(get_color("foo").is_err());
+  }
+
+  #[test]
+  fn match_basic_color() {
+    assert!(is_basic_color(capture::Color::Default, false));
+    assert!(is_basic_color(capture::Color::Indexed(7), false));
+    assert!(is_basic_color(capture::Color::Indexed(9), true));
+    assert!(!is_basic_color(capture::Color::Indexed(9), false));
+    assert!(!is_basic_color(capture::Color::Rgb(0, 0, 0), true));
+
+    assert_eq!(
+      get_basic_color(capture::Color::Default),
+      (Color::Default, false)
+    );
+    assert_eq!(
+      get_basic_color(capture::Color::Indexed(1)),
+      (Color::Red, false)
+    );
+    assert_eq!(
+      get_basic_color(capture::Color::Indexed(12)),
+      (Color::Blue, true)
+    );
+  }
+
+  #[test]
+  fn match_capture_color() {
+    let fallback = Color::White;
+
+    assert_eq!(
+      get_capture_color(capture::Color::Default, fallback),
+      Color::White
+    );
+    assert_eq!(
+      get_capture_color(capture::Color::Indexed(208), fallback),
+      Color::Byte(208)
+    );
+    assert_eq!(
+      get_capture_color(capture::Color::Rgb(255, 0, 0), fallback),
+      Color::Byte(196)
+    );
+    assert_eq!(
+      get_capture_color(capture::Color::Rgb(128, 128, 128), fallback),
+      Color::Byte(244)
+    );
   }
 }
@@ -0,0 +1,504 @@
+use clap::ArgMatches;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use tmux_thumbs::error::Error;
+use tmux_thumbs::template;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+  Text,
+  Number,
+  Flag,
+  List,
+  Patterns,
+  Table,
+}
+
+/// Keys of the config file, named like the command line options, with the argument
+/// they provide a value for.
+const KEYS: [(&str, &str, Kind); 26] = [
+  ("alphabet", "alphabet", Kind::Text),
+  ("position", "position", Kind::Text),
+  ("fg-color", "foreground_color", Kind::Text),
+  ("bg-color", "background_color", Kind::Text),
+  ("hint-fg-color", "hint_foreground_color", Kind::Text),
+  ("hint-bg-color", "hint_background_color", Kind::Text),
+  ("select-fg-color", "select_foreground_color", Kind::Text),
+  ("command", "command", Kind::Text),
+  ("upcase-command", "upcase_command", Kind::Text),
+  ("separator", "separator", Kind::Text),
+  ("history", "history", Kind::Number),
+  ("reverse", "reverse", Kind::Flag),
+  ("cursor", "cursor", Kind::Flag),
+  ("unique", "unique", Kind::Flag),
+  ("multiline", "multiline", Kind::Flag),
+  ("window", "window", Kind::Flag),
+  ("session", "session", Kind::Flag),
+  ("multi", "multi", Kind::Flag),
+  ("shell", "shell", Kind::Flag),
+  ("pipe", "pipe", Kind::Flag),
+  ("exclude", "exclude", Kind::Patterns),
+  ("disable", "disable", Kind::List),
+  ("priority", "priority", Kind::List),
+  ("regexp", "regexp", Kind::Table),
+  ("action", "action", Kind::Table),
+  ("prefix", "prefix", Kind::Table),
+];
+
+#[derive(Clone, Debug, PartialEq)]
+enum Setting {
+  Text(String),
+  Flag(bool),
+  List(Vec<String>),
+  Table(Vec<(String, String)>),
+}
+
+/// Settings of the config file or the tmux options, by argument name. The ones of the
+/// chosen profile replace the top level ones, and add to them for tables.
+#[derive(Debug, Default)]
+pub struct Config {
+  settings: HashMap<&'static str, Setting>,
+}
+
+impl Config {
+  /// Loads the given file, or the default one if it exists.
+  pub fn load(path: Option<&str>, profile: Option<&str>) -> Result<Config, Error> {
+    let (path, required) = match path {
+      Some(path) => (PathBuf::from(path), true),
+      None => match default_path() {
+        Some(path) => (path, false),
+        None => return Config::parse("", profile).map_err(Error::Config),
+      },
+    };
+
+    let text = match fs::read_to_string(&path) {
+      Ok(text) => text,
+      Err(ref e) if !required && e.kind() == io::ErrorKind::NotFound => String::new(),
+      Err(e) => return Err(Error::Input(path.display().to_string(), e)),
+    };
+
+    Config::parse(&text, profile)
+      .map_err(|message| Error::Config(format!("{}: {}", path.display(), message)))
+  }
+
+  /// Reads the `@thumbs-*` tmux options, named like the keys of the config file.
+  /// Regexps and exclusions are numbered, as in `@thumbs-regexp-1`, while actions and
+  /// prefixes are named, as in `@thumbs-action-url`. Other options are left alone.
+  pub fn tmux(options: &[(String, String)]) -> Result<Config, Error> {
+    let mut config = Config::default();
+
+    for (key, value) in options {
+      config.set_option(key, value).map_err(Error::Config)?;
+    }
+
+    Ok(config)
+  }
+
+  fn parse(text: &str, profile: Option<&str>) -> Result<Config, String> {
+    let table = text
+      .parse::<toml::Table>()
+      .map_err(|e| e.message().to_string())?;
+
+    let mut config = Config::default();
+    let mut profiles = None;
+
+    for (key, value) in table.iter() {
+      if key == "profile" {
+        profiles = Some(value.as_table().ok_or_else(|| invalid(key, "a table"))?);
+      } else {
+        config.set(key, value)?;
+      }
+    }
+
+    if let Some(name) = profile {
+      let settings = profiles
+        .and_then(|profiles| profiles.get(name))
+        .ok_or_else(|| format!("unknown profile {}", name))?
+        .as_table()
+        .ok_or_else(|| invalid(&format!("profile.{}", name), "a table"))?;
+
+      for (key, value) in settings.iter() {
+        match key.as_str() {
+          "profile" => return Err(unknown(&format!("profile.{}.{}", name, key))),
+          key => config.set(key, value)?,
+        }
+      }
+    }
+
+    Ok(config)
+  }
+
+  fn set(&mut self, key: &str, value: &toml::Value) -> Result<(), String> {
+    let (_, name, kind) = KEYS
+      .iter()
+      .find(|(known, _, _)| *known == key)
+      .ok_or_else(|| unknown(key))?;
+
+    let setting = match (kind, value) {
+      (Kind::Text, toml::Value::String(text)) => Setting::Text(text.clone()),
+      (Kind::Number, toml::Value::Integer(number)) if *number >= 0 => {
+        Setting::Text(number.to_string())
+      }
+      (Kind::Flag, toml::Value::Boolean(flag)) => Setting::Flag(*flag),
+      (Kind::List | Kind::Patterns, toml::Value::Array(items)) => Setting::List(
+        items
+          .iter()
+          .map(|item| item.as_str().map(|item| item.to_string()))
+          .collect::<Option<_>>()
+          .ok_or_else(|| invalid(key, "a list of strings"))?,
+      ),
+      (Kind::Table, toml::Value::Table(table)) => {
+        let mut entries = table
+          .iter()
+          .map(|(name, item)| item.as_str().map(|item| (name.clone(), item.to_string())))
+          .collect::<Option<Vec<_>>>()
+          .ok_or_else(|| invalid(key, "a table of strings"))?;
+
+        // Profile entries come first, so they win over the top level ones
+        if let Some(Setting::Table(previous)) = self.settings.get(name) {
+          entries.extend(
+            previous
+              .iter()
+              .filter(|(name, _)| !table.contains_key(name))
+              .cloned(),
+          );
+        }
+
+        Setting::Table(entries)
+      }
+      (Kind::Text, _) => return Err(invalid(key, "a string")),
+      (Kind::Number, _) => return Err(invalid(key, "a positive number")),
+      (Kind::Flag, _) => return Err(invalid(key, "true or false")),
+      (Kind::List | Kind::Patterns, _) => return Err(invalid(key, "a list of strings")),
+      (Kind::Table, _) => return Err(invalid(key, "a table of strings")),
+    };
+
+    self.settings.insert(name, setting);
+
+    Ok(())
+  }
+
+  fn set_option(&mut self, key: &str, value: &str) -> Result<(), String> {
+    let option = format!("@thumbs-{}", key);
+
+    if let Some((_, name, kind)) = KEYS.iter().find(|(known, _, _)| *known == key) {
+      let setting = match kind {
+        Kind::Text | Kind::Number => Setting::Text(value.to_string()),
+        Kind::Flag => match value {
+          "1" | "on" | "true" => Setting::Flag(true),
+          "" | "0" | "off" | "false" => Setting::Flag(false),
+          _ => return Err(invalid(&option, "1 or 0")),
+        },
+        Kind::List => Setting::List(
+          value
+            .split(',')
+            .filter(|item| !item.is_empty())
+            .map(|item| item.to_string())
+            .collect(),
+        ),
+        // Regexps may hold commas, so each option adds a whole one, along the numbered
+        // ones
+        Kind::Patterns => {
+          let mut patterns = match self.settings.remove(name) {
+            Some(Setting::List(patterns)) => patterns,
+            _ => vec![],
+          };
+
+          patterns.push(value.to_string());
+          Setting::List(patterns)
+        }
+        Kind::Table => return Err(unknown(&option)),
+      };
+
+      self.settings.insert(name, setting);
+
+      return Ok(());
+    }
+
+    let (kind, item) = match key.find('-') {
+      Some(index) => (&key[..index], &key[index + 1..]),
+      None => return Ok(()),
+    };
+
+    // Numbered options only add a value, named ones an entry
+    let (name, named) = match kind {
+      "regexp" => ("regexp", false),
+      "exclude" => ("exclude", false),
+      "action" => ("action", true),
+      "prefix" => ("prefix", true),
+      _ => return Ok(()),
+    };
+
+    let setting = self.settings.entry(name).or_insert_with(|| {
+      if named {
+        Setting::Table(vec![])
+      } else {
+        Setting::List(vec![])
+      }
+    });
+
+    match setting {
+      Setting::List(items) => items.push(value.to_string()),
+      Setting::Table(entries) => entries.push((item.to_string(), value.to_string())),
+      _ => {}
+    }
+
+    Ok(())
+  }
+}
+
+/// `$XDG_CONFIG_HOME/tmux-thumbs/config.toml`, or `~/.config/tmux-thumbs/config.toml`.
+fn default_path() -> Option<PathBuf> {
+  let directory = match env::var_os("XDG_CONFIG_HOME") {
+    Some(directory) if !directory.is_empty() => PathBuf::from(directory),
+    _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+  };
+
+  Some(directory.join("tmux-thumbs").join("config.toml"))
+}
+
+fn unknown(key: &str) -> String {
+  format!("unknown key {}", key)
+}
+
+fn invalid(key: &str, expected: &str) -> String {
+  format!("{} must be {}", key, expected)
+}
+
+/// Every setting, from the command line first, then the given configs in order, and
+/// then the defaults of the command line options.
+pub struct Settings<'a> {
+  args: &'a ArgMatches<'a>,
+  configs: Vec<Config>,
+}
+
+impl<'a> Settings<'a> {
+  pub fn new(args: &'a ArgMatches<'a>, configs: Vec<Config>) -> Settings<'a> {
+    Settings {
+      args: args,
+      configs: configs,
+    }
+  }
+
+  pub fn value(&self, name: &str) -> Option<&str> {
+    if self.args.occurrences_of(name) > 0 {
+      return self.args.value_of(name);
+    }
+
+    self
+      .configs
+      .iter()
+      .find_map(|config| match config.settings.get(name) {
+        Some(Setting::Text(text)) => Some(text.as_str()),
+        _ => None,
+      })
+      .or_else(|| self.args.value_of(name))
+  }
+
+  pub fn flag(&self, name: &str) -> bool {
+    self.args.is_present(name)
+      || self
+        .configs
+        .iter()
+        .find_map(|config| match config.settings.get(name) {
+          Some(Setting::Flag(flag)) => Some(*flag),
+          _ => None,
+        })
+        .unwrap_or(false)
+  }
+
+  /// Lists given in the command line replace the ones of the configs.
+  pub fn values(&self, name: &str) -> Vec<&str> {
+    if let Some(items) = self.args.values_of(name) {
+      return items.collect();
+    }
+
+    self
+      .configs
+      .iter()
+      .find_map(|config| match config.settings.get(name) {
+        Some(Setting::List(items)) => Some(items.iter().map(|item| item.as_str()).collect()),
+        _ => None,
+      })
+      .unwrap_or_default()
+  }
+
+  /// Named settings, given as `name=value` in the command line or as tables in the
+  /// configs. The first ones win when several have the same name.
+  pub fn named(&self, name: &str) -> Result<Vec<(&str, &str)>, Error> {
+    let mut entries = match self.args.values_of(name) {
+      Some(items) => items.map(template::action).collect::<Result<Vec<_>, _>>()?,
+      None => vec![],
+    };
+
+    for config in self.configs.iter() {
+      if let Some(Setting::Table(table)) = config.settings.get(name) {
+        for (key, value) in table.iter() {
+          if !entries.iter().any(|(name, _)| name == key) {
+            entries.push((key, value));
+          }
+        }
+      }
+    }
+
+    Ok(entries)
+  }
+
+  /// Custom regexps of the command line and of every config. The ones of a table are
+  /// named after their key, and the others after their groups.
+  pub fn regexps(&self) -> Vec<(Option<&str>, &str)> {
+    let mut regexps = self
+      .args
+      .values_of("regexp")
+      .map_or(vec![], |items| items.map(|regexp| (None, regexp)).collect());
+
+    for config in self.configs.iter() {
+      match config.settings.get("regexp") {
+        Some(Setting::List(items)) => {
+          regexps.extend(items.iter().map(|regexp| (None, regexp.as_str())))
+        }
+        Some(Setting::Table(table)) => regexps.extend(
+          table
+            .iter()
+            .map(|(name, regexp)| (Some(name.as_str()), regexp.as_str())),
+        ),
+        _ => {}
+      }
+    }
+
+    regexps
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const CONFIG: &str = r#"
+alphabet = "dvorak"
+reverse = true
+history = 200
+disable = ["sha"]
+
+[action]
+url = "xdg-open {}"
+path = "code {}"
+
+[regexp]
+ticket = 'TICKET-\d+'
+
+[profile.review]
+alphabet = "colemak"
+unique = true
+
+[profile.review.action]
+url = "firefox {}"
+"#;
+
+  #[test]
+  fn parse_settings() {
+    let config = Config::parse(CONFIG, None).unwrap();
+
+    assert_eq!(
+      config.settings["alphabet"],
+      Setting::Text("dvorak".to_string())
+    );
+    assert_eq!(config.settings["reverse"], Setting::Flag(true));
+    assert_eq!(config.settings["history"], Setting::Text("200".to_string()));
+    assert_eq!(
+      config.settings["disable"],
+      Setting::List(vec!["sha".to_string()])
+    );
+    assert!(!config.settings.contains_key("unique"));
+  }
+
+  #[test]
+  fn parse_profile() {
+    let config = Config::parse(CONFIG, Some("review")).unwrap();
+    let actions = vec![
+      ("url".to_string(), "firefox {}".to_string()),
+      ("path".to_string(), "code {}".to_string()),
+    ];
+
+    assert_eq!(
+      config.settings["alphabet"],
+      Setting::Text("colemak".to_string())
+    );
+    assert_eq!(config.settings["reverse"], Setting::Flag(true));
+    assert_eq!(config.settings["unique"], Setting::Flag(true));
+    assert_eq!(config.settings["action"], Setting::Table(actions));
+  }
+
+  #[test]
+  fn parse_tmux_options() {
+    let options = [
+      ("key", "F"),
+      ("reverse", "1"),
+      ("unique", "0"),
+      ("command", "tmux set-buffer -- {}"),
+      ("disable", "number,sha"),
+      ("exclude", "PID-[0-9]{1,3}"),
+      ("exclude-1", "[0-9a-f]{7,40}"),
+      ("regexp-1", "[a-z]+@[a-z]+.com"),
+      ("regexp-2", r"TICKET-\d+"),
+      ("action-url", "xdg-open {}"),
+      ("prefix-o", "code {}"),
+    ]
+    .iter()
+    .map(|(key, value)| (key.to_string(), value.to_string()))
+    .collect::<Vec<_>>();
+    let config = Config::tmux(&options).unwrap();
+
+    assert_eq!(config.settings["reverse"], Setting::Flag(true));
+    assert_eq!(config.settings["unique"], Setting::Flag(false));
+    assert_eq!(
+      config.settings["command"],
+      Setting::Text("tmux set-buffer -- {}".to_string())
+    );
+    assert_eq!(
+      config.settings["disable"],
+      Setting::List(vec!["number".to_string(), "sha".to_string()])
+    );
+    assert_eq!(
+      config.settings["exclude"],
+      Setting::List(vec![
+        "PID-[0-9]{1,3}".to_string(),
+        "[0-9a-f]{7,40}".to_string()
+      ])
+    );
+    assert_eq!(
+      config.settings["regexp"],
+      Setting::List(vec![
+        "[a-z]+@[a-z]+.com".to_string(),
+        r"TICKET-\d+".to_string()
+      ])
+    );
+    assert_eq!(
+      config.settings["action"],
+      Setting::Table(vec![("url".to_string(), "xdg-open {}".to_string())])
+    );
+    assert_eq!(
+      config.settings["prefix"],
+      Setting::Table(vec![("o".to_string(), "code {}".to_string())])
+    );
+    assert!(!config.settings.contains_key("key"));
+
+    let options = vec![("multi".to_string(), "yes please".to_string())];
+
+    assert!(Config::tmux(&options).is_err());
+  }
+
+  #[test]
+  fn parse_errors() {
+    assert!(Config::parse(CONFIG, Some("foo")).is_err());
+    assert!(Config::parse("alphabets = \"dvorak\"", None).is_err());
+    assert!(Config::parse("reverse = \"yes\"", None).is_err());
+    assert!(Config::parse("history = -1", None).is_err());
+    assert!(Config::parse("disable = [1]", None).is_err());
+    assert!(Config::parse("[profile.foo]\nbar = 1", Some("foo")).is_err());
+    assert!(Config::parse("alphabet = ", None).is_err());
+  }
+}
@@ -0,0 +1,49 @@
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum Error {
+  NoMatches,
+  UnknownAlphabet(String),
+  UnknownColor(String),
+  UnknownPattern(String),
+  InvalidRegexp(String, regex::Error),
+  InvalidHistory(String),
+  InvalidCommand(String),
+  InvalidAction(String),
+  InvalidKey(String),
+  Config(String),
+  Capture(String),
+  Input(String, io::Error),
+  Command(String, io::Error),
+  CommandFailed(String, String),
+  Terminal(String),
+  Signal(io::Error),
+}
+
+impl fmt::Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Error::NoMatches => write!(f, "No matches"),
+      Error::UnknownAlphabet(name) => write!(f, "Unknown alphabet: {}", name),
+      Error::UnknownColor(name) => write!(f, "Unknown color: {}", name),
+      Error::UnknownPattern(name) => write!(f, "Unknown pattern: {}", name),
+      Error::InvalidRegexp(regexp, error) => write!(f, "Invalid regexp {}: {}", regexp, error),
+      Error::InvalidHistory(lines) => write!(f, "Invalid history: {}", lines),
+      Error::InvalidCommand(command) => write!(f, "Invalid command: {}", command),
+      Error::InvalidAction(action) => {
+        write!(f, "Invalid action, expected name=command: {}", action)
+      }
+      Error::InvalidKey(key) => write!(f, "Invalid prefix key: {}", key),
+      Error::Config(message) => write!(f, "Invalid config: {}", message),
+      Error::Capture(message) => write!(f, "Couldn't capture the pane: {}", message),
+      Error::Input(path, error) => write!(f, "Couldn't read {}: {}", path, error),
+      Error::Command(command, error) => write!(f, "Couldn't run {}: {}", command, error),
+      Error::CommandFailed(command, reason) => write!(f, "{} failed: {}", command, reason),
+      Error::Terminal(message) => write!(f, "Couldn't open the terminal: {}", message),
+      Error::Signal(error) => write!(f, "Couldn't watch signals: {}", error),
+    }
+  }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,45 @@
+//! Hinting of the text in a terminal, as done by the `tmux-thumbs` binary.
+//!
+//! The text, usually the output of `tmux capture-pane -e`, is parsed into [`capture::Line`]s.
+//! A [`state::Matcher`] holds the builtin and custom patterns, and a [`state::State`] finds
+//! their matches in the lines and assigns them hints from an [`alphabets::Alphabet`]:
+//!
+//! ```
+//! use tmux_thumbs::alphabets::get_alphabet;
+//! use tmux_thumbs::capture;
+//! use tmux_thumbs::state::{Matcher, State};
+//!
+//! let lines = capture::parse("commit \x1b[33mfd70b5695\x1b[m in /var/log");
+//! let matcher = Matcher::new(&[], &[], &[], &[]).unwrap();
+//! let state = State::new(lines, get_alphabet("qwerty").unwrap(), matcher, None);
+//!
+//! let matches = state.matches(false, false);
+//!
+//! assert_eq!(matches[0].pattern, "sha");
+//! assert_eq!(matches[0].text, "fd70b5695");
+//! assert_eq!(matches[1].hint, Some("s".to_string()));
+//! ```
+//!
+//! Everything the matches hold is owned, so they can outlive the state.
+//!
+//! The `view` showing the hints and the `colors` it uses need termbox, and come
+//! with the default `tui` feature. Disable it to only match and hint.
+
+/// Letters to build the hints with.
+pub mod alphabets;
+/// Parsing of the captured text, its styles and screen columns.
+pub mod capture;
+/// Color names and conversion of the captured colors for the terminal.
+#[cfg(feature = "tui")]
+pub mod colors;
+/// Every error tmux-thumbs reports.
+pub mod error;
+/// Listing of the matches as a table or JSON lines.
+pub mod list;
+/// The patterns, their matches and hint assignment.
+pub mod state;
+/// Command templates with placeholders.
+pub mod template;
+/// The interactive hints, drawn with termbox.
+#[cfg(feature = "tui")]
+pub mod view;
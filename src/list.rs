@@ -0,0 +1,121 @@
+use super::state::Match;
+use std::fmt::Write;
+
+/// Formats the matches in aligned columns, under a header.
+pub fn table(matches: &[Match]) -> String {
+  let width = |header: &str, values: &dyn Fn(&Match) -> usize| {
+    matches
+      .iter()
+      .map(values)
+      .max()
+      .unwrap_or(0)
+      .max(header.len())
+  };
+
+  let pattern_width = width("PATTERN", &|mat| mat.pattern.len());
+  let hint_width = width("HINT", &|mat| {
+    mat.hint.as_ref().map_or(0, |hint| hint.len())
+  });
+
+  let mut table = format!(
+    "{:>5} {:>5}  {:<pattern$}  {:<hint$}  TEXT\n",
+    "LINE",
+    "COL",
+    "PATTERN",
+    "HINT",
+    pattern = pattern_width,
+    hint = hint_width
+  );
+
+  for mat in matches {
+    let _ = writeln!(
+      table,
+      "{:>5} {:>5}  {:<pattern$}  {:<hint$}  {}",
+      mat.y,
+      mat.x,
+      mat.pattern,
+      mat.hint.as_ref().map_or("", |hint| hint.as_str()),
+      mat.text,
+      pattern = pattern_width,
+      hint = hint_width
+    );
+  }
+
+  table
+}
+
+/// Formats a match as a JSON object in a single line.
+pub fn json(mat: &Match) -> String {
+  format!(
+    "{{\"x\":{},\"y\":{},\"pattern\":{},\"text\":{},\"hint\":{}}}",
+    mat.x,
+    mat.y,
+    string(&mat.pattern),
+    string(&mat.text),
+    mat
+      .hint
+      .as_ref()
+      .map_or("null".to_string(), |hint| string(hint))
+  )
+}
+
+/// Quotes a JSON string, escaping quotes, backslashes and control characters.
+fn string(text: &str) -> String {
+  let mut quoted = String::with_capacity(text.len() + 2);
+
+  quoted.push('"');
+
+  for c in text.chars() {
+    match c {
+      '"' => quoted.push_str("\\\""),
+      '\\' => quoted.push_str("\\\\"),
+      '\n' => quoted.push_str("\\n"),
+      '\t' => quoted.push_str("\\t"),
+      c if c.is_control() => {
+        let _ = write!(quoted, "\\u{:04x}", c as u32);
+      }
+      c => quoted.push(c),
+    }
+  }
+
+  quoted.push('"');
+  quoted
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn sample(hint: Option<&str>, text: &str) -> Match {
+    Match {
+      x: 4,
+      y: 1,
+      pattern: "path".to_string(),
+      text: text.to_string(),
+      hint: hint.map(|hint| hint.to_string()),
+      segments: vec![],
+    }
+  }
+
+  #[test]
+  fn list_json() {
+    assert_eq!(
+      json(&sample(Some("a"), "/tmp/\"foo\"\\bar\t\x07")),
+      r#"{"x":4,"y":1,"pattern":"path","text":"/tmp/\"foo\"\\bar\t\u0007","hint":"a"}"#
+    );
+    assert_eq!(
+      json(&sample(None, "/tmp")),
+      r#"{"x":4,"y":1,"pattern":"path","text":"/tmp","hint":null}"#
+    );
+  }
+
+  #[test]
+  fn list_table() {
+    let matches = vec![sample(Some("a"), "/tmp"), sample(Some("bb"), "/var/log")];
+
+    assert_eq!(
+      table(&matches),
+      " LINE   COL  PATTERN  HINT  TEXT\n    1     4  path     a     /tmp\n    1     4  path     bb    /var/log\n"
+    );
+  }
+}
@@ -1,28 +1,136 @@
 extern crate clap;
 extern crate rustbox;
 
-mod alphabets;
-mod colors;
-mod state;
-mod view;
+mod config;
+mod restore;
+mod tmux;
 
 use self::clap::{App, Arg};
 use clap::crate_version;
-use std::process::Command;
+use config::Settings;
+use std::env;
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::process::{self, Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tmux_thumbs::error::{self, Error};
+use tmux_thumbs::template::Placeholders;
+use tmux_thumbs::{alphabets, capture, colors, list, state, template, view};
 
-fn exec_command(command: String) -> std::process::Output {
+/// Exit code when there is nothing to hint in the pane.
+const EXIT_NO_MATCHES: i32 = 2;
+
+/// Column and line of the cursor in the captured lines.
+type Cursor = Option<(usize, usize)>;
+
+/// Commands of the pane mode, to jump to the chosen pane or swap it with the user's one.
+const PANE_JUMP_COMMAND: &str = "tmux switch-client -t {}";
+const PANE_SWAP_COMMAND: &str = "tmux swap-pane -s {} -t {pane_id}";
+
+fn exec_command(command: String) -> Result<std::process::Output, Error> {
   let args: Vec<_> = command.split(" ").collect();
 
-  return Command::new(args[0])
-    .args(&args[1..])
+  return exec_args(&args);
+}
+
+fn exec_args<S: AsRef<str>>(args: &[S]) -> Result<std::process::Output, Error> {
+  let program = args[0].as_ref();
+
+  return Command::new(program)
+    .args(args[1..].iter().map(|arg| arg.as_ref()))
     .output()
-    .expect("Couldn't run it");
+    .map_err(|e| Error::Command(program.to_string(), e));
+}
+
+/// Runs a pick command, writing the input to its stdin if any. Fails with its stderr
+/// when it doesn't succeed. Its stderr goes to a removed temporary file, as clipboard
+/// tools that fork to keep the selection can hold it open long after they exit.
+fn spawn_command(args: &[String], input: Option<&str>) -> Result<(), Error> {
+  let program = args[0].as_str();
+  let failed = |e| Error::Command(program.to_string(), e);
+
+  let mut stderr = stderr_file().map_err(failed)?;
+
+  let mut child = Command::new(program)
+    .args(&args[1..])
+    .stdin(if input.is_some() {
+      Stdio::piped()
+    } else {
+      Stdio::null()
+    })
+    .stdout(Stdio::null())
+    .stderr(stderr.try_clone().map_err(failed)?)
+    .spawn()
+    .map_err(failed)?;
+
+  if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+    // Commands are free to exit without reading it all
+    match stdin.write_all(input.as_bytes()) {
+      Err(ref e) if e.kind() == io::ErrorKind::BrokenPipe => {}
+      result => result.map_err(failed)?,
+    }
+  }
+
+  let status = child.wait().map_err(failed)?;
+
+  if status.success() {
+    return Ok(());
+  }
+
+  let mut message = String::new();
+
+  stderr.seek(SeekFrom::Start(0)).map_err(failed)?;
+  stderr.read_to_string(&mut message).map_err(failed)?;
+
+  let reason = match message.trim() {
+    "" => status.to_string(),
+    message => message.to_string(),
+  };
+
+  Err(Error::CommandFailed(program.to_string(), reason))
+}
+
+/// Creates an anonymous temporary file, removed as soon as it's open.
+fn stderr_file() -> io::Result<fs::File> {
+  let nanos = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map_or(0, |duration| duration.subsec_nanos());
+  let path = env::temp_dir().join(format!("tmux-thumbs-{}-{}", process::id(), nanos));
+
+  let file = fs::OpenOptions::new()
+    .read(true)
+    .write(true)
+    .create_new(true)
+    .open(&path)?;
+
+  fs::remove_file(&path)?;
+
+  Ok(file)
+}
+
+/// Shows a message in the tmux status line.
+fn display_message(message: &str) {
+  let _ = Command::new("tmux")
+    .args(&["display-message", message])
+    .output();
 }
 
 fn app_args<'a>() -> clap::ArgMatches<'a> {
   return App::new("tmux-thumbs")
     .version(crate_version!())
     .about("A lightning fast version of tmux-fingers, copy/pasting tmux like vimium/vimperator")
+    .arg(
+      Arg::with_name("config")
+        .help("Read the settings from this file instead of ~/.config/tmux-thumbs/config.toml")
+        .long("config")
+        .takes_value(true),
+    )
+    .arg(
+      Arg::with_name("profile")
+        .help("Use the settings of this profile of the config file")
+        .long("profile")
+        .takes_value(true),
+    )
     .arg(
       Arg::with_name("alphabet")
         .help("Sets the alphabet")
@@ -66,12 +174,50 @@ fn app_args<'a>() -> clap::ArgMatches<'a> {
         .long("reverse")
         .short("r"),
     )
+    .arg(
+      Arg::with_name("cursor")
+        .help("Give the shortest hints to the matches closest to the cursor")
+        .long("cursor")
+        .short("c"),
+    )
     .arg(
       Arg::with_name("unique")
         .help("Don't show duplicated hints for the same match")
         .long("unique")
         .short("u"),
     )
+    .arg(
+      Arg::with_name("multiline")
+        .help("Match text that wraps across multiple lines")
+        .long("multiline")
+        .short("m"),
+    )
+    .arg(
+      Arg::with_name("window")
+        .help("Hint every visible pane of the window of the tmux pane")
+        .long("window")
+        .short("w")
+        .conflicts_with("input"),
+    )
+    .arg(
+      Arg::with_name("session")
+        .help("List the matches of every pane of the session of the tmux pane")
+        .long("session")
+        .short("s")
+        .conflicts_with_all(&["input", "window"]),
+    )
+    .arg(
+      Arg::with_name("panes")
+        .help("Hint the other panes of the session to jump to one, or swap it with an upcase hint")
+        .long("panes")
+        .conflicts_with_all(&["input", "window", "session"]),
+    )
+    .arg(
+      Arg::with_name("history")
+        .help("Capture this number of lines of history")
+        .long("history")
+        .takes_value(true),
+    )
     .arg(
       Arg::with_name("position")
         .help("Hint position")
@@ -79,24 +225,84 @@ fn app_args<'a>() -> clap::ArgMatches<'a> {
         .default_value("left")
         .short("p"),
     )
+    .arg(
+      Arg::with_name("input")
+        .help("Hint this file instead of a tmux pane, or stdin with -, and print the selected text")
+        .long("input")
+        .takes_value(true)
+        .conflicts_with("tmux_pane"),
+    )
+    .arg(
+      Arg::with_name("list")
+        .help("Print every match in a table instead of showing the hints")
+        .long("list"),
+    )
+    .arg(
+      Arg::with_name("json")
+        .help("Print every match as a line of JSON instead of showing the hints")
+        .long("json"),
+    )
     .arg(
       Arg::with_name("tmux_pane")
         .help("Get this tmux pane as reference pane")
         .long("tmux-pane")
         .takes_value(true),
     )
+    .arg(
+      Arg::with_name("launch")
+        .help("Show the hints over the current tmux pane, with the rest of the arguments")
+        .long("launch")
+        .conflicts_with_all(&["tmux_pane", "input", "list", "json"]),
+    )
     .arg(
       Arg::with_name("command")
-        .help("Pick command")
+        .help("Pick command. Placeholders: {} or {text}, {pattern}, {line}, {x}, {y}, {pane_id} and {pane_cwd}")
         .long("command")
         .default_value("tmux set-buffer {}"),
     )
     .arg(
       Arg::with_name("upcase_command")
-        .help("Upcase command")
+        .help("Upcase command, with the same placeholders as the pick one")
         .long("upcase-command")
         .default_value("tmux paste-buffer"),
     )
+    .arg(
+      Arg::with_name("action")
+        .help("Command to run instead of the pick one for a pattern, as name=command")
+        .long("action")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1),
+    )
+    .arg(
+      Arg::with_name("multi")
+        .help("Toggle several matches with their hints and confirm them with Enter")
+        .long("multi"),
+    )
+    .arg(
+      Arg::with_name("separator")
+        .help("Separator to join the text of several matches")
+        .long("separator")
+        .default_value(" "),
+    )
+    .arg(
+      Arg::with_name("prefix")
+        .help("Key to type before a hint to run another command, as key=command")
+        .long("prefix")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1),
+    )
+    .arg(
+      Arg::with_name("shell")
+        .help("Run the commands with sh -c, quoting the placeholders")
+        .long("shell"),
+    )
+    .arg(
+      Arg::with_name("pipe")
+        .help("Write the selected text to the stdin of the commands")
+        .long("pipe"),
+    )
     .arg(
       Arg::with_name("regexp")
         .help("Use this regexp as extra pattern to match")
@@ -105,67 +311,689 @@ fn app_args<'a>() -> clap::ArgMatches<'a> {
         .takes_value(true)
         .multiple(true),
     )
+    .arg(
+      Arg::with_name("exclude")
+        .help("Use this regexp as extra pattern to never match")
+        .long("exclude")
+        .takes_value(true)
+        .multiple(true),
+    )
+    .arg(
+      Arg::with_name("disable")
+        .help("Disable these builtin patterns")
+        .long("disable")
+        .takes_value(true)
+        .multiple(true)
+        .use_delimiter(true),
+    )
+    .arg(
+      Arg::with_name("priority")
+        .help("Try these builtin patterns before the others")
+        .long("priority")
+        .takes_value(true)
+        .multiple(true)
+        .use_delimiter(true),
+    )
     .get_matches();
 }
 
-fn main() {
-  let args = app_args();
-  let alphabet = args.value_of("alphabet").unwrap();
-  let position = args.value_of("position").unwrap();
-  let reverse = args.is_present("reverse");
-  let unique = args.is_present("unique");
-  let regexp = if let Some(items) = args.values_of("regexp") {
-    items.collect::<Vec<_>>()
-  } else {
-    [].to_vec()
+/// Captures the pane, with the width where its lines wrap in multiline mode, and the
+/// cursor position if the hints go by it.
+fn capture(settings: &Settings) -> Result<(Vec<capture::Line>, Option<usize>, Cursor), Error> {
+  let multiline = settings.flag("multiline");
+  let history = match history(settings)? {
+    Some(start) => format!(" -S {}", start),
+    None => "".to_string(),
   };
 
-  let foreground_color = colors::get_color(args.value_of("foreground_color").unwrap());
-  let background_color = colors::get_color(args.value_of("background_color").unwrap());
-  let hint_foreground_color = colors::get_color(args.value_of("hint_foreground_color").unwrap());
-  let hint_background_color = colors::get_color(args.value_of("hint_background_color").unwrap());
-  let select_foreground_color =
-    colors::get_color(args.value_of("select_foreground_color").unwrap());
-
-  let command = args.value_of("command").unwrap();
-  let upcase_command = args.value_of("upcase_command").unwrap();
-  let tmux_subcommand = if let Some(pane) = args.value_of("tmux_pane") {
+  let tmux_subcommand = if let Some(pane) = settings.value("tmux_pane") {
     format!(" -t {}", pane)
   } else {
     "".to_string()
   };
 
-  let execution = exec_command(format!("tmux capture-pane -e -J -p{}", tmux_subcommand));
-  let output = String::from_utf8_lossy(&execution.stdout);
-  let lines = output.split("\n").collect::<Vec<&str>>();
-
-  let mut state = state::State::new(&lines, alphabet, &regexp);
-
-  let selected = {
-    let mut viewbox = view::View::new(
-      &mut state,
-      reverse,
-      unique,
-      position,
-      select_foreground_color,
-      foreground_color,
-      background_color,
-      hint_foreground_color,
-      hint_background_color,
-    );
+  // Joined lines only tell where tmux wrapped the text, so in multiline mode they are
+  // split back in rows of the pane width
+  let wrap = if multiline {
+    let execution = exec_command(format!(
+      "tmux display-message -p{} #{{pane_width}}",
+      tmux_subcommand
+    ))?;
+
+    String::from_utf8_lossy(&execution.stdout)
+      .trim()
+      .parse::<usize>()
+      .ok()
+  } else {
+    None
+  };
+
+  let execution = exec_command(format!(
+    "tmux capture-pane -e -J{} -p{}",
+    history, tmux_subcommand
+  ))?;
+
+  if !execution.status.success() {
+    let message = String::from_utf8_lossy(&execution.stderr);
+
+    return Err(Error::Capture(message.trim().to_string()));
+  }
+
+  let lines = parse(&String::from_utf8_lossy(&execution.stdout));
+
+  let cursor = if settings.flag("cursor") {
+    let execution = exec_command(format!(
+      "tmux display-message -p{} #{{cursor_x}},#{{cursor_y}},#{{pane_width}},#{{pane_height}}",
+      tmux_subcommand
+    ))?;
+    let numbers = String::from_utf8_lossy(&execution.stdout)
+      .trim()
+      .split(',')
+      .map(|number| number.parse::<usize>().ok())
+      .collect::<Option<Vec<_>>>();
+
+    match numbers.as_deref() {
+      Some(&[x, y, width, height]) => locate(&lines, (x, y), (width, height), multiline),
+      _ => None,
+    }
+  } else {
+    None
+  };
+
+  Ok((lines, wrap, cursor))
+}
+
+/// Finds the cursor, at the given column and row of the pane, in the captured lines.
+/// The pane rows are the last ones of the capture, where wrapped lines are joined, or
+/// split back in rows in multiline mode.
+fn locate(
+  lines: &[capture::Line],
+  (x, y): (usize, usize),
+  (width, height): (usize, usize),
+  multiline: bool,
+) -> Cursor {
+  // The line and the column where every row starts
+  let mut rows = Vec::new();
+
+  for (index, line) in lines.iter().enumerate() {
+    let mut offset = 0;
+
+    for row in line.rows(width) {
+      rows.push((index, line.column(offset)));
+      offset += row.text.len();
+    }
+  }
+
+  let row = rows.len().saturating_sub(height) + y;
+  let (index, start) = *rows.get(row)?;
+
+  if multiline {
+    Some((x, row))
+  } else {
+    Some((start + x, index))
+  }
+}
+
+/// First line to capture with `-S`, going back the given lines of history.
+fn history(settings: &Settings) -> Result<Option<String>, Error> {
+  match settings.value("history") {
+    Some(lines) => {
+      let lines = lines
+        .parse::<usize>()
+        .map_err(|_| Error::InvalidHistory(lines.to_string()))?;
+
+      Ok(Some(format!("-{}", lines)))
+    }
+    None => Ok(None),
+  }
+}
+
+/// Runs a tmux command reading the panes, failing with its message.
+fn read_panes(args: &[&str]) -> Result<String, Error> {
+  let execution = exec_args(args)?;
+
+  if !execution.status.success() {
+    let message = String::from_utf8_lossy(&execution.stderr);
+
+    return Err(Error::Capture(message.trim().to_string()));
+  }
+
+  Ok(String::from_utf8_lossy(&execution.stdout).into_owned())
+}
+
+/// Lists the panes of the window of the tmux pane, or of its session with `-s`.
+fn list_panes(settings: &Settings, options: &[&str]) -> Result<String, Error> {
+  let mut args = vec!["tmux", "list-panes"];
+
+  args.extend(options);
+
+  if let Some(pane) = settings.value("tmux_pane") {
+    args.extend(&["-t", pane]);
+  }
+
+  read_panes(&args)
+}
+
+/// Captures every visible pane of the window of the tmux pane, only the zoomed one if
+/// any, along the screen position of the cursor of the active one. Lines are only
+/// joined in multiline mode, to be split back in rows of the pane.
+fn capture_window(settings: &Settings) -> Result<(Vec<state::Pane>, Cursor), Error> {
+  let output = list_panes(
+    settings,
+    &[
+      "-F",
+      "#{pane_id} #{pane_left} #{pane_top} #{pane_width} #{?window_zoomed_flag,#{pane_active},1} #{pane_active} #{cursor_x} #{cursor_y}",
+    ],
+  )?;
+
+  let multiline = settings.flag("multiline");
+  let mut panes = Vec::new();
+  let mut cursor = None;
+
+  let number = |value: &str| value.parse::<usize>().unwrap_or(0);
+
+  for line in output.lines() {
+    let (pane, x, y, width) = match line.split(' ').collect::<Vec<_>>()[..] {
+      [pane, x, y, width, "1", active, cursor_x, cursor_y] => {
+        if active == "1" {
+          cursor = Some((number(x) + number(cursor_x), number(y) + number(cursor_y)));
+        }
+
+        (pane, number(x), number(y), number(width))
+      }
+      _ => continue,
+    };
+
+    let mut args = vec!["tmux", "capture-pane", "-e", "-p", "-t", pane];
 
-    viewbox.present()
+    if multiline {
+      args.push("-J");
+    }
+
+    let output = read_panes(&args)?;
+
+    panes.push(state::Pane {
+      id: pane.to_string(),
+      x: x,
+      y: y,
+      lines: parse(&output),
+      wrap: if multiline { Some(width) } else { None },
+    });
+  }
+
+  Ok((panes, cursor))
+}
+
+/// Captures every pane of the session of the tmux pane, by id and named after their
+/// window.
+fn capture_session(
+  settings: &Settings,
+) -> Result<Vec<(String, String, Vec<capture::Line>)>, Error> {
+  let start = history(settings)?;
+  let output = list_panes(
+    settings,
+    &[
+      "-s",
+      "-F",
+      "#{pane_id} #{window_index}.#{pane_index} #{window_name}",
+    ],
+  )?;
+
+  let mut panes = Vec::new();
+
+  for line in output.lines() {
+    let (pane, name) = match line.find(' ') {
+      Some(index) => (&line[..index], &line[index + 1..]),
+      None => continue,
+    };
+    let mut args = vec!["tmux", "capture-pane", "-e", "-J", "-p", "-t", pane];
+
+    if let Some(start) = &start {
+      args.extend(&["-S", start]);
+    }
+
+    let output = read_panes(&args)?;
+
+    panes.push((pane.to_string(), name.to_string(), parse(&output)));
+  }
+
+  Ok(panes)
+}
+
+/// Every other pane of the session of the tmux pane, described by its window and the
+/// command it runs.
+fn panes(settings: &Settings) -> Result<Vec<(String, String)>, Error> {
+  let output = list_panes(
+    settings,
+    &[
+      "-s",
+      "-F",
+      "#{pane_id}\t#{window_index}.#{pane_index}\t#{window_name}\t#{pane_current_command} #{pane_current_path}",
+    ],
+  )?;
+  let current = settings.value("tmux_pane");
+
+  let panes = output
+    .lines()
+    .filter_map(|line| match line.split('\t').collect::<Vec<_>>()[..] {
+      [pane, index, window, command] => Some((pane, index, window, command)),
+      _ => None,
+    })
+    .filter(|(pane, _, window, _)| Some(*pane) != current && *window != tmux::WINDOW_NAME)
+    .collect::<Vec<_>>();
+
+  let index_width = panes.iter().map(|pane| pane.1.len()).max().unwrap_or(0);
+  let window_width = panes.iter().map(|pane| pane.2.len()).max().unwrap_or(0);
+
+  Ok(
+    panes
+      .into_iter()
+      .map(|(pane, index, window, command)| {
+        let description = format!(
+          "{:<index$}  {:<window$}  {}",
+          index,
+          window,
+          command,
+          index = index_width,
+          window = window_width
+        );
+
+        (pane.to_string(), description)
+      })
+      .collect(),
+  )
+}
+
+/// Parses a capture, keeping its blank rows at the bottom of the pane so the last page
+/// is the visible screen. Only the newline ending the last row goes.
+fn parse(output: &str) -> Vec<capture::Line> {
+  capture::parse(output.strip_suffix('\n').unwrap_or(output))
+}
+
+/// Reads the text to hint from a file, or from stdin with `-`.
+fn read_input(path: &str) -> Result<String, Error> {
+  let mut input = Vec::new();
+  let result = if path == "-" {
+    io::stdin().read_to_end(&mut input)
+  } else {
+    fs::File::open(path).and_then(|mut file| file.read_to_end(&mut input))
   };
 
-  if let Some(pane) = args.value_of("tmux_pane") {
-    exec_command(format!("tmux swap-pane -t {}", pane));
+  result.map_err(|e| Error::Input(path.to_string(), e))?;
+
+  Ok(String::from_utf8_lossy(&input).into_owned())
+}
+
+/// Parses the input, or the captured pane if there is none.
+fn load(settings: &Settings) -> Result<(Vec<capture::Line>, Option<usize>, Cursor), Error> {
+  match settings.value("input") {
+    Some(path) => Ok((parse(&read_input(path)?), None, None)),
+    None => capture(settings),
+  }
+}
+
+fn matcher(settings: &Settings) -> Result<state::Matcher, Error> {
+  state::Matcher::named(
+    &settings.regexps(),
+    &settings.values("exclude"),
+    &settings.values("disable"),
+    &settings.values("priority"),
+  )
+}
+
+/// The lines to hint: the input, the captured pane, every pane of its window, or the
+/// list of matches, or of panes, of its session.
+fn state(settings: &Settings, alphabet: alphabets::Alphabet) -> Result<state::State, Error> {
+  if settings.flag("panes") {
+    return Ok(state::State::choices("pane", panes(settings)?, alphabet));
+  }
+
+  let matcher = matcher(settings)?;
+
+  if settings.flag("session") && settings.value("input").is_none() {
+    return Ok(state::State::list(
+      capture_session(settings)?,
+      alphabet,
+      matcher,
+    ));
+  }
+
+  let (mut state, cursor) = if settings.flag("window") && settings.value("input").is_none() {
+    let (panes, cursor) = capture_window(settings)?;
+
+    (state::State::window(panes, alphabet, matcher), cursor)
+  } else {
+    let (lines, wrap, cursor) = load(settings)?;
+
+    (state::State::new(lines, alphabet, matcher, wrap), cursor)
   };
 
-  if let Some((text, paste)) = selected {
-    exec_command(str::replace(command, "{}", text.as_str()));
+  match cursor {
+    Some((x, y)) if settings.flag("cursor") => state.set_cursor(x, y),
+    _ => {}
+  }
+
+  Ok(state)
+}
+
+/// Prints every match with its hint, without any interaction.
+fn list(settings: &Settings) -> Result<(), Error> {
+  let alphabet = alphabets::get_alphabet(settings.value("alphabet").unwrap())?;
+  let reverse = settings.flag("reverse");
+  let unique = settings.flag("unique");
+
+  let state = state(settings, alphabet)?;
+  let matches = state.matches(reverse, unique);
+
+  if matches.is_empty() {
+    return Err(Error::NoMatches);
+  }
+
+  if settings.flag("json") {
+    for mat in matches.iter() {
+      println!("{}", list::json(mat));
+    }
+  } else {
+    print!("{}", list::table(&matches));
+  }
+
+  Ok(())
+}
+
+/// Lets the user pick a match of the captured pane or the input.
+fn pick(
+  settings: &Settings,
+  prefixes: &[view::Prefix],
+) -> Result<Option<(Placeholders<'static>, view::Pick)>, Error> {
+  let alphabet = alphabets::get_alphabet(settings.value("alphabet").unwrap())?;
+
+  // Prefixes can't be told apart from hints otherwise
+  for prefix in prefixes {
+    if let rustbox::Key::Char(ch) = prefix.key {
+      if alphabet.contains(ch) {
+        return Err(Error::InvalidKey(prefix.name.to_string()));
+      }
+    }
+  }
+
+  let position = settings.value("position").unwrap();
+  let reverse = settings.flag("reverse");
+  let unique = settings.flag("unique");
+  let multi = settings.flag("multi");
+  let separator = settings.value("separator").unwrap();
+
+  let foreground_color = colors::get_color(settings.value("foreground_color").unwrap())?;
+  let background_color = colors::get_color(settings.value("background_color").unwrap())?;
+  let hint_foreground_color = colors::get_color(settings.value("hint_foreground_color").unwrap())?;
+  let hint_background_color = colors::get_color(settings.value("hint_background_color").unwrap())?;
+  let select_foreground_color =
+    colors::get_color(settings.value("select_foreground_color").unwrap())?;
+
+  let mut state = state(settings, alphabet)?;
+
+  let mut viewbox = view::View::new(
+    &mut state,
+    reverse,
+    unique,
+    position,
+    prefixes,
+    multi,
+    select_foreground_color,
+    foreground_color,
+    background_color,
+    hint_foreground_color,
+    hint_background_color,
+  );
+
+  // Several matches are joined in the text, and the rest comes from the first one
+  let selected = viewbox.present()?.map(|(chosen, pick)| {
+    let mut placeholders = Placeholders::new();
+    let mat = &chosen[0];
+    let texts = chosen
+      .iter()
+      .map(|mat| mat.text.as_str())
+      .collect::<Vec<_>>();
+
+    placeholders.insert("text", texts.join(separator));
+    placeholders.insert("pattern", mat.pattern.to_string());
+    placeholders.insert(
+      "line",
+      state
+        .line(mat)
+        .map_or(String::new(), |line| line.text.clone()),
+    );
+    placeholders.insert("x", mat.x.to_string());
+    placeholders.insert("y", mat.y.to_string());
+
+    // Matches of a window or a session come from their own pane
+    if let Some(pane) = state.pane(mat) {
+      placeholders.insert("pane_id", pane.to_string());
+    }
+
+    (placeholders, pick)
+  });
+
+  Ok(selected)
+}
+
+/// Keys to type before a hint to run another command.
+fn prefixes<'a>(settings: &'a Settings) -> Result<Vec<view::Prefix<'a>>, Error> {
+  settings
+    .named("prefix")?
+    .into_iter()
+    .map(|(name, command)| view::Prefix::new(name, command))
+    .collect()
+}
+
+/// Runs the command of the typed prefix if any, else the action of the pattern or the
+/// pick command if it has none. Upcase hints always run the pick command and then the
+/// upcase one, to paste the text. In pane mode, the chosen pane is jumped to instead,
+/// or swapped with the user's one for upcase hints.
+fn run(
+  settings: &Settings,
+  actions: &[(&str, &str)],
+  prefixes: &[view::Prefix],
+  selected: Option<(Placeholders<'static>, view::Pick)>,
+) -> Result<(), Error> {
+  let upcase_command = settings.value("upcase_command").unwrap();
+  let shell = settings.flag("shell");
+  let pipe = settings.flag("pipe");
+  let panes = settings.flag("panes");
+
+  if let Some((mut placeholders, pick)) = selected {
+    let pattern = placeholders["pattern"].as_str();
+    let action = actions.iter().find(|(name, _)| *name == pattern);
+    let command = match (pick, action) {
+      (view::Pick::Prefix(index), _) => prefixes[index].command,
+      (view::Pick::Copy, Some((_, command))) => command,
+      // Panes are swapped instead of pasted
+      (view::Pick::Paste, _) if panes => PANE_SWAP_COMMAND,
+      _ if panes => PANE_JUMP_COMMAND,
+      _ => settings.value("command").unwrap(),
+    };
+    let paste = pick == view::Pick::Paste && !panes;
+
+    let pane = placeholders
+      .get("pane_id")
+      .cloned()
+      .or_else(|| settings.value("tmux_pane").map(|pane| pane.to_string()))
+      .or_else(|| env::var("TMUX_PANE").ok());
+
+    if let Some(pane) = pane {
+      if [command, upcase_command]
+        .iter()
+        .any(|command| template::uses(command, "pane_cwd"))
+      {
+        let output = exec_args(&[
+          "tmux",
+          "display-message",
+          "-p",
+          "-t",
+          &pane,
+          "#{pane_current_path}",
+        ])?;
+        let cwd = String::from_utf8_lossy(&output.stdout)
+          .trim_end()
+          .to_string();
+
+        placeholders.insert("pane_cwd", cwd);
+      }
+
+      placeholders.insert("pane_id", pane);
+    }
+
+    let input = if pipe {
+      placeholders.get("text").map(|text| text.as_str())
+    } else {
+      None
+    };
+
+    spawn_command(&template::render(command, shell, &placeholders)?, input)?;
 
     if paste {
-      exec_command(upcase_command.to_string());
+      spawn_command(
+        &template::render(upcase_command, shell, &placeholders)?,
+        input,
+      )?;
+    }
+  }
+
+  Ok(())
+}
+
+/// Lets the user pick a match and runs its command with the user's pane back in place.
+fn thumbs(settings: &Settings, restore: Option<&restore::Restore>) -> Result<(), Error> {
+  if let Some(restore) = restore {
+    restore.watch_signals()?;
+  }
+
+  if settings.flag("list") || settings.flag("json") {
+    return list(settings);
+  }
+
+  let actions = settings.named("action")?;
+  let prefixes = prefixes(settings)?;
+  let selected = pick(settings, &prefixes);
+
+  if let Some(restore) = restore {
+    restore.restore();
+  }
+
+  // Filter mode leaves the text to whoever reads the output
+  if settings.value("input").is_some() {
+    if let Some((placeholders, _)) = selected? {
+      println!("{}", placeholders["text"]);
+    }
+
+    return Ok(());
+  }
+
+  run(settings, &actions, &prefixes, selected?)
+}
+
+/// The tmux options apply only when tmux-thumbs is launched on a pane, and come before
+/// the config file.
+fn settings<'a>(args: &'a clap::ArgMatches<'a>) -> Result<Settings<'a>, Error> {
+  let mut configs = vec![];
+
+  if args.is_present("tmux_pane") || args.is_present("launch") {
+    configs.push(config::Config::tmux(&tmux::options()?)?);
+  }
+
+  configs.push(config::Config::load(
+    args.value_of("config"),
+    args.value_of("profile"),
+  )?);
+
+  Ok(Settings::new(args, configs))
+}
+
+fn main() {
+  let args = app_args();
+
+  // Brings the user's pane back even if tmux-thumbs panics or gets killed
+  let restore = args.value_of("tmux_pane").map(restore::Restore::new);
+
+  let result = settings(&args).and_then(|settings| {
+    if args.is_present("launch") {
+      tmux::launch(settings.flag("window") || settings.flag("session") || settings.flag("panes"))
+    } else {
+      thumbs(&settings, restore.as_ref())
+    }
+  });
+
+  if let Err(error) = &result {
+    eprintln!("tmux-thumbs: {}", error);
+
+    if args.is_present("tmux_pane") || args.is_present("launch") {
+      display_message(&format!("tmux-thumbs: {}", error));
+    }
+  }
+
+  // Killing the temporary window ends this process too, so it goes last
+  drop(restore);
+
+  if let Err(error) = result {
+    process::exit(match error {
+      Error::NoMatches => EXIT_NO_MATCHES,
+      _ => 1,
+    });
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::time::{Duration, Instant};
+
+  fn args(script: &str) -> Vec<String> {
+    vec!["sh".to_string(), "-c".to_string(), script.to_string()]
+  }
+
+  #[test]
+  fn parse_blank_rows() {
+    let lines = parse("$ ls\nfoo\n$\n\n\n");
+
+    assert_eq!(lines.len(), 5);
+    assert_eq!(lines[2].text, "$");
+    assert_eq!(lines[4].text, "");
+  }
+
+  #[test]
+  fn locate_cursor() {
+    let lines = parse("lorem\n$ ls\nabcdefghijklmnopqrstuvw\n$\n");
+
+    // The wrapped line below takes three rows of the pane
+    assert_eq!(locate(&lines, (3, 0), (10, 5), false), Some((3, 1)));
+    assert_eq!(locate(&lines, (4, 2), (10, 5), false), Some((14, 2)));
+    assert_eq!(locate(&lines, (1, 4), (10, 5), false), Some((1, 3)));
+    assert_eq!(locate(&lines, (4, 2), (10, 5), true), Some((4, 3)));
+    assert_eq!(locate(&lines, (0, 5), (10, 5), false), None);
+  }
+
+  #[test]
+  fn spawn_background_child() {
+    let start = Instant::now();
+
+    // The background sleep keeps the stderr of the command open
+    spawn_command(&args("sleep 5 & echo copied"), Some("text")).unwrap();
+
+    assert!(start.elapsed() < Duration::from_secs(3));
+  }
+
+  #[test]
+  fn spawn_failing_command() {
+    match spawn_command(&args("cat; echo nope >&2; exit 3"), Some("text")) {
+      Err(Error::CommandFailed(program, reason)) => {
+        assert_eq!(program, "sh");
+        assert_eq!(reason, "nope");
+      }
+      result => panic!("unexpected result: {:?}", result),
+    }
+
+    match spawn_command(&args("exit 3"), None) {
+      Err(Error::CommandFailed(_, reason)) => assert_eq!(reason, "exit status: 3"),
+      result => panic!("unexpected result: {:?}", result),
     }
   }
 }
@@ -0,0 +1,125 @@
+use super::error::Error;
+use super::tmux::WINDOW_NAME;
+use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+use signal_hook::iterator::{Handle, Signals};
+use std::env;
+use std::process::{self, Command};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Puts the user's pane back in its window, whichever way tmux-thumbs exits. The pane
+/// is swapped back as soon as `restore` is called or the guard is dropped, and then the
+/// temporary window holding the thumbs pane is killed.
+pub struct Restore {
+  panes: Arc<Panes>,
+}
+
+impl Restore {
+  pub fn new(pane: &str) -> Restore {
+    Restore {
+      panes: Arc::new(Panes {
+        pane: pane.to_string(),
+        own_pane: env::var("TMUX_PANE").ok(),
+        swapped: AtomicBool::new(false),
+        closed: AtomicBool::new(false),
+        signals: Mutex::new(None),
+      }),
+    }
+  }
+
+  /// Restores the pane and exits when tmux-thumbs is hung up, interrupted or terminated.
+  pub fn watch_signals(&self) -> Result<(), Error> {
+    let mut signals = Signals::new(&[SIGHUP, SIGINT, SIGTERM]).map_err(Error::Signal)?;
+    let panes = self.panes.clone();
+
+    *self.panes.signals.lock().unwrap() = Some(signals.handle());
+
+    thread::spawn(move || {
+      if let Some(signal) = signals.forever().next() {
+        panes.swap();
+        panes.close();
+
+        process::exit(128 + signal);
+      }
+    });
+
+    Ok(())
+  }
+
+  /// Swaps the user's pane back, so the selected text can be pasted into it.
+  pub fn restore(&self) {
+    self.panes.swap();
+  }
+}
+
+impl Drop for Restore {
+  fn drop(&mut self) {
+    self.panes.swap();
+    self.panes.close();
+  }
+}
+
+/// The user's pane and the thumbs one. Each step runs only once, even when a signal
+/// arrives in the middle.
+struct Panes {
+  pane: String,
+  own_pane: Option<String>,
+  swapped: AtomicBool,
+  closed: AtomicBool,
+  signals: Mutex<Option<Handle>>,
+}
+
+impl Panes {
+  /// The user's pane is left alone when it isn't in the temporary window, which happens
+  /// when tmux-thumbs runs in a popup, or exits before `--launch` swaps it out.
+  fn swap(&self) {
+    if self.swapped.swap(true, Ordering::SeqCst) {
+      return;
+    }
+
+    if window_name(&self.pane).as_deref() == Some(WINDOW_NAME) {
+      tmux(&["swap-pane", "-t", &self.pane]);
+    }
+  }
+
+  /// Kills the temporary window, and with it the thumbs pane. That hangs tmux-thumbs
+  /// up, so the signals aren't watched anymore, and it exits as it meant to.
+  fn close(&self) {
+    if self.closed.swap(true, Ordering::SeqCst) {
+      return;
+    }
+
+    if let Some(signals) = self.signals.lock().unwrap().take() {
+      signals.close();
+    }
+
+    if let Some(own_pane) = &self.own_pane {
+      if window_name(own_pane).as_deref() == Some(WINDOW_NAME) {
+        tmux(&["kill-window", "-t", own_pane]);
+      }
+    }
+  }
+}
+
+fn window_name(pane: &str) -> Option<String> {
+  let output = Command::new("tmux")
+    .args(&["display-message", "-p", "-t", pane, "#{window_name}"])
+    .output()
+    .ok()?;
+
+  if output.status.success() {
+    Some(
+      String::from_utf8_lossy(&output.stdout)
+        .trim_end()
+        .to_string(),
+    )
+  } else {
+    None
+  }
+}
+
+/// Runs a tmux command on a best effort basis: there is nothing left to do if it fails.
+fn tmux(args: &[&str]) {
+  let _ = Command::new("tmux").args(args).output();
+}
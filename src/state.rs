@@ -1,9 +1,11 @@
-use regex::Regex;
-use std::collections::HashMap;
+use super::alphabets::Alphabet;
+use super::capture::{self, Cell, Line};
+use super::error::Error;
+use regex::{self, Regex, RegexSet};
+use std::collections::{HashMap, HashSet};
 use std::fmt;
-
-const EXCLUDE_PATTERNS: [(&'static str, &'static str); 1] =
-  [("bash", r"[[:cntrl:]]\[([0-9]{1,2};)?([0-9]{1,2})?m")];
+use std::ops::Range;
+use unicode_width::UnicodeWidthStr;
 
 const PATTERNS: [(&'static str, &'static str); 11] = [
   ("markdown_url", r"\[[^]]*\]\(([^)]+)\)"),
@@ -25,16 +27,28 @@ const PATTERNS: [(&'static str, &'static str); 11] = [
   ("number", r"[0-9]{4,}"),
 ];
 
+/// The part of a match in a single line, at the screen column where it starts.
+#[derive(Clone, Debug)]
+pub struct Segment {
+  pub x: i32,
+  pub y: i32,
+  pub width: i32,
+  pub text: String,
+}
+
+/// A match of a pattern, at the screen position where it starts. Matches can span
+/// several lines in multiline mode, with a segment for each one.
 #[derive(Clone)]
-pub struct Match<'a> {
+pub struct Match {
   pub x: i32,
   pub y: i32,
-  pub pattern: &'a str,
-  pub text: &'a str,
+  pub pattern: String,
+  pub text: String,
   pub hint: Option<String>,
+  pub segments: Vec<Segment>,
 }
 
-impl<'a> fmt::Debug for Match<'a> {
+impl fmt::Debug for Match {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     write!(
       f,
@@ -48,148 +62,659 @@ impl<'a> fmt::Debug for Match<'a> {
   }
 }
 
-impl<'a> PartialEq for Match<'a> {
+impl PartialEq for Match {
   fn eq(&self, other: &Match) -> bool {
     self.x == other.x && self.y == other.y
   }
 }
 
-pub struct State<'a> {
-  pub lines: &'a Vec<&'a str>,
-  alphabet: &'a str,
-  regexp: &'a Vec<&'a str>,
+/// A run of consecutive lines that is matched as a single piece of text, with the
+/// screen row of each one and the column where they start.
+struct Haystack<'a> {
+  text: String,
+  x: usize,
+  rows: Vec<(usize, &'a Line)>,
 }
 
-impl<'a> State<'a> {
-  pub fn new(lines: &'a Vec<&'a str>, alphabet: &'a str, regexp: &'a Vec<&'a str>) -> State<'a> {
-    State {
-      lines: lines,
-      alphabet: alphabet,
-      regexp: regexp,
+impl<'a> Haystack<'a> {
+  /// Splits the byte range `start..end` of the haystack into one segment per line, placed
+  /// at the screen column where it starts.
+  fn segments(&self, start: usize, end: usize) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut row_start = 0;
+
+    for (index, line) in self.rows.iter() {
+      let row_end = row_start + line.text.len();
+
+      if start < row_end && end > row_start {
+        let from = start.max(row_start) - row_start;
+        let to = end.min(row_end) - row_start;
+
+        let x = line.column(from);
+
+        segments.push(Segment {
+          x: (self.x + x) as i32,
+          y: *index as i32,
+          width: (line.column(to) - x) as i32,
+          text: line.text[from..to].to_string(),
+        });
+      }
+
+      row_start = row_end;
     }
+
+    segments
   }
+}
 
-  pub fn matches(&self, reverse: bool, unique: bool) -> Vec<Match<'a>> {
-    let mut matches = Vec::new();
+/// Every pattern compiled once, ordered by priority: exclusions, custom patterns and then
+/// the builtin ones.
+pub struct Matcher {
+  patterns: Vec<(String, Regex)>,
+  exclusions: usize,
+  set: RegexSet,
+}
 
-    let exclude_patterns = EXCLUDE_PATTERNS
+impl Matcher {
+  /// Builds the pattern set. Builtin patterns can be disabled by name, and the ones
+  /// listed in `priority` are tried first while the rest keep their default order.
+  /// Text matching the `exclude` patterns is never hinted.
+  pub fn new(
+    regexp: &[&str],
+    exclude: &[&str],
+    disable: &[&str],
+    priority: &[&str],
+  ) -> Result<Matcher, Error> {
+    let regexp = regexp
       .iter()
-      .map(|tuple| (tuple.0, Regex::new(tuple.1).unwrap()))
+      .map(|regexp| (None, *regexp))
       .collect::<Vec<_>>();
 
-    let custom_patterns = self
-      .regexp
+    Matcher::named(&regexp, exclude, disable, priority)
+  }
+
+  /// Like `new`, but custom patterns can be given a name instead of being named after
+  /// their groups.
+  pub fn named(
+    regexp: &[(Option<&str>, &str)],
+    exclude: &[&str],
+    disable: &[&str],
+    priority: &[&str],
+  ) -> Result<Matcher, Error> {
+    for name in disable.iter().chain(priority.iter()) {
+      if !PATTERNS.iter().any(|tuple| tuple.0 == *name) {
+        return Err(Error::UnknownPattern(name.to_string()));
+      }
+    }
+
+    let compile =
+      |regexp: &str| Regex::new(regexp).map_err(|e| Error::InvalidRegexp(regexp.to_string(), e));
+
+    let exclude_patterns = exclude
+      .iter()
+      .map(|regexp| Ok(("exclude".to_string(), compile(regexp)?)))
+      .collect::<Result<Vec<_>, Error>>()?;
+
+    let custom_patterns = regexp
+      .iter()
+      .map(|(name, regexp)| {
+        let pattern = compile(regexp)?;
+        let name = name.map_or_else(|| label(&pattern), |name| name.to_string());
+
+        Ok((name, pattern))
+      })
+      .collect::<Result<Vec<_>, Error>>()?;
+
+    let mut builtin = PATTERNS
       .iter()
-      .map(|regexp| ("custom", Regex::new(regexp).expect("Invalid custom regexp")))
+      .filter(|tuple| !disable.contains(&tuple.0))
       .collect::<Vec<_>>();
 
-    let patterns = PATTERNS
+    builtin.sort_by_key(|tuple| {
+      priority
+        .iter()
+        .position(|name| *name == tuple.0)
+        .unwrap_or(priority.len())
+    });
+
+    let patterns = builtin
+      .iter()
+      .map(|tuple| (tuple.0.to_string(), Regex::new(tuple.1).unwrap()));
+
+    let exclusions = exclude_patterns.len();
+    let all_patterns = exclude_patterns
+      .into_iter()
+      .chain(custom_patterns)
+      .chain(patterns)
+      .collect::<Vec<_>>();
+
+    // Every pattern already compiled on its own, so the set can't fail short of
+    // exceeding the size limit.
+    let set = RegexSet::new(all_patterns.iter().map(|tuple| tuple.1.as_str()))
+      .map_err(|e| Error::InvalidRegexp("every pattern".to_string(), e))?;
+
+    Ok(Matcher {
+      patterns: all_patterns,
+      exclusions: exclusions,
+      set: set,
+    })
+  }
+
+  /// Finds the non overlapping matches of all patterns in a single pass. When several
+  /// patterns match at the same position the one with the highest priority wins, and
+  /// exclusions swallow their matches without reporting them. Returns the pattern name
+  /// and the range of the text to copy, which is the `match` capture group or the first
+  /// one if any.
+  fn find(&self, haystack: &str) -> Vec<(&str, Range<usize>)> {
+    let mut found = Vec::new();
+
+    // Only the patterns that match somewhere in the haystack need to be searched, and
+    // each one keeps its next match around until the scan moves past its start.
+    let mut candidates = self
+      .set
+      .matches(haystack)
       .iter()
-      .map(|tuple| (tuple.0, Regex::new(tuple.1).unwrap()))
+      .map(|index| (index, self.next(index, haystack, 0)))
       .collect::<Vec<_>>();
 
-    let all_patterns = [exclude_patterns, custom_patterns, patterns].concat();
-
-    for (index, line) in self.lines.iter().enumerate() {
-      let mut chunk: &str = line;
-      let mut offset: i32 = 0;
-
-      loop {
-        let submatches = all_patterns
-          .iter()
-          .filter_map(|tuple| match tuple.1.find_iter(chunk).nth(0) {
-            Some(m) => Some((tuple.0, tuple.1.clone(), m)),
-            None => None,
-          })
-          .collect::<Vec<_>>();
-        let first_match_option = submatches
-          .iter()
-          .min_by(|x, y| x.2.start().cmp(&y.2.start()));
-
-        if let Some(first_match) = first_match_option {
-          let (name, pattern, matching) = first_match;
-          let text = matching.as_str();
-
-          if let Some(captures) = pattern.captures(text) {
-            let (subtext, substart) = if let Some(capture) = captures.get(1) {
-              (capture.as_str(), capture.start())
-            } else {
-              (matching.as_str(), 0)
-            };
-
-            // Never hint or broke bash color sequences
-            if *name != "bash" {
-              matches.push(Match {
-                x: offset + matching.start() as i32 + substart as i32,
-                y: index as i32,
-                pattern: name,
-                text: subtext,
-                hint: None,
-              });
-            }
-
-            chunk = chunk.get(matching.end()..).expect("Unknown chunk");
-            offset = offset + matching.end() as i32;
-          } else {
-            panic!("No matching?");
+    let mut position = 0;
+
+    loop {
+      for (index, candidate) in candidates.iter_mut() {
+        if let Some(matching) = candidate {
+          if matching.start() < position {
+            *candidate = self.next(*index, haystack, position);
           }
-        } else {
-          break;
         }
       }
+
+      let first_match = candidates
+        .iter()
+        .filter_map(|(index, candidate)| candidate.map(|matching| (*index, matching)))
+        .min_by_key(|(index, matching)| (matching.start(), *index));
+
+      let (index, matching) = match first_match {
+        Some(first_match) => first_match,
+        None => break,
+      };
+
+      let (name, pattern) = &self.patterns[index];
+      let range = pattern
+        .captures_at(haystack, matching.start())
+        .and_then(|captures| captures.name("match").or_else(|| captures.get(1)))
+        .map_or(matching.range(), |capture| capture.range());
+
+      if index >= self.exclusions {
+        found.push((name.as_str(), range));
+      }
+
+      position = matching.end();
     }
 
-    let alphabet = super::alphabets::get_alphabet(self.alphabet);
-    let mut hints = alphabet.hints(matches.len());
+    found
+  }
 
-    // This looks wrong but we do a pop after
-    if !reverse {
-      hints.reverse();
-    } else {
-      matches.reverse();
-      hints.reverse();
+  /// Finds the next non empty match of a pattern starting at the given position.
+  fn next<'h>(&self, index: usize, haystack: &'h str, position: usize) -> Option<regex::Match<'h>> {
+    let mut start = position;
+
+    loop {
+      let matching = self.patterns[index].1.find_at(haystack, start)?;
+
+      if matching.end() > matching.start() {
+        return Some(matching);
+      }
+
+      start = matching.end() + haystack[matching.end()..].chars().next()?.len_utf8();
+    }
+  }
+}
+
+/// Custom patterns are named after their first named group other than `match`.
+fn label(pattern: &Regex) -> String {
+  pattern
+    .capture_names()
+    .flatten()
+    .find(|name| *name != "match")
+    .map_or("custom".into(), str::to_string)
+}
+
+/// One of the panes of a window, captured to be hinted along the others. Its lines
+/// are drawn from the screen column `x` and row `y`, split in rows of the `wrap` width
+/// if any, and `id` is the one of its tmux pane.
+pub struct Pane {
+  pub id: String,
+  pub x: usize,
+  pub y: usize,
+  pub lines: Vec<Line>,
+  pub wrap: Option<usize>,
+}
+
+/// The captured lines, and how to find and hint the matches in them.
+pub struct State {
+  pub lines: Vec<Line>,
+  panes: Vec<Pane>,
+  listed: Option<Vec<(Match, Line, Option<String>)>>,
+  cursor: Option<(usize, usize)>,
+  alphabet: Alphabet,
+  matcher: Matcher,
+}
+
+impl State {
+  /// With a `wrap` width, the lines are captured joined, and split back in the rows of
+  /// the pane, so matches go on from a wrapped row to the next one.
+  pub fn new(lines: Vec<Line>, alphabet: Alphabet, matcher: Matcher, wrap: Option<usize>) -> State {
+    State {
+      lines: rows(lines, wrap),
+      panes: Vec::new(),
+      listed: None,
+      cursor: None,
+      alphabet: alphabet,
+      matcher: matcher,
+    }
+  }
+
+  /// Hints several panes at once. Every pane is matched on its own, while the lines
+  /// hold all of them side by side, as they show up in the window.
+  pub fn window(panes: Vec<Pane>, alphabet: Alphabet, matcher: Matcher) -> State {
+    let panes = panes
+      .into_iter()
+      .map(|pane| Pane {
+        lines: rows(pane.lines, pane.wrap),
+        ..pane
+      })
+      .collect::<Vec<_>>();
+
+    State {
+      lines: compose(&panes),
+      panes: panes,
+      listed: None,
+      cursor: None,
+      alphabet: alphabet,
+      matcher: matcher,
+    }
+  }
+
+  /// Lists the matches of several panes, given by id and name, instead of showing them,
+  /// one per line along its pattern and the name of its pane. The same text is only
+  /// listed once, for the first pane where it shows up.
+  pub fn list(
+    panes: Vec<(String, String, Vec<Line>)>,
+    alphabet: Alphabet,
+    matcher: Matcher,
+  ) -> State {
+    let mut names = Vec::new();
+    let mut stacked = Vec::new();
+    let mut y = 0;
+
+    // Every pane is matched on its own, but the rows go on from one to the next
+    for (id, name, lines) in panes {
+      let height = lines.len();
+
+      names.push((y, name));
+      stacked.push(Pane {
+        id: id,
+        x: 0,
+        y: y,
+        lines: lines,
+        wrap: None,
+      });
+
+      y += height;
+    }
+
+    let mut state = State {
+      lines: Vec::new(),
+      panes: stacked,
+      listed: None,
+      cursor: None,
+      alphabet: alphabet,
+      matcher: matcher,
+    };
+
+    let mut seen = HashSet::new();
+    let found = state
+      .find()
+      .into_iter()
+      .filter(|mat| seen.insert(mat.text.clone()))
+      .collect::<Vec<_>>();
+
+    let pattern_width = found.iter().map(|mat| mat.pattern.len()).max().unwrap_or(0);
+    let mut entries = Vec::new();
+    let mut sources = Vec::new();
+
+    for mat in found {
+      let name = names
+        .iter()
+        .rev()
+        .find(|(start, _)| *start <= mat.y as usize)
+        .map_or("", |(_, name)| name.as_str());
+      let description = format!("{:<width$}  {}", mat.pattern, name, width = pattern_width);
+
+      sources.push((
+        state.line(&mat).cloned().unwrap_or_default(),
+        state.pane(&mat).map(|pane| pane.to_string()),
+      ));
+      entries.push((mat, description));
+    }
+
+    state.lines = listing(&mut entries);
+    state.panes.clear();
+    state.listed = Some(
+      entries
+        .into_iter()
+        .map(|(mat, _)| mat)
+        .zip(sources)
+        .map(|(mat, (line, pane))| (mat, line, pane))
+        .collect(),
+    );
+    state
+  }
+
+  /// Lets the user choose among the given texts, listed one per line along their
+  /// description, as if they were matches of the given pattern.
+  pub fn choices(pattern: &str, choices: Vec<(String, String)>, alphabet: Alphabet) -> State {
+    let mut entries = choices
+      .into_iter()
+      .map(|(text, description)| {
+        let mat = Match {
+          x: 0,
+          y: 0,
+          pattern: pattern.to_string(),
+          text: text,
+          hint: None,
+          segments: Vec::new(),
+        };
+
+        (mat, description)
+      })
+      .collect::<Vec<_>>();
+    let lines = listing(&mut entries);
+
+    State {
+      listed: Some(
+        entries
+          .into_iter()
+          .map(|(mat, _)| mat)
+          .zip(lines.iter().cloned())
+          .map(|(mat, line)| (mat, line, None))
+          .collect(),
+      ),
+      lines: lines,
+      panes: Vec::new(),
+      cursor: None,
+      alphabet: alphabet,
+      matcher: Matcher {
+        patterns: Vec::new(),
+        exclusions: 0,
+        set: RegexSet::empty(),
+      },
+    }
+  }
+
+  /// Tells if the lines list the matches or the choices, instead of showing a capture.
+  pub fn is_list(&self) -> bool {
+    self.listed.is_some()
+  }
+
+  /// Gives the shortest hints to the matches closest to the cursor, at the given column
+  /// and line, instead of following the lines.
+  pub fn set_cursor(&mut self, x: usize, y: usize) {
+    self.cursor = Some((x, y));
+  }
+
+  /// The captured line where the match starts, out of its own pane for a window.
+  pub fn line(&self, mat: &Match) -> Option<&Line> {
+    if let Some(listed) = &self.listed {
+      return listed.get(mat.y as usize).map(|(_, line, _)| line);
+    }
+
+    if self.panes.is_empty() {
+      return self.lines.get(mat.y as usize);
+    }
+
+    self
+      .source(mat)
+      .map(|pane| &pane.lines[mat.y as usize - pane.y])
+  }
+
+  /// The id of the tmux pane where the match comes from, when there are several.
+  pub fn pane(&self, mat: &Match) -> Option<&str> {
+    if let Some(listed) = &self.listed {
+      return listed
+        .get(mat.y as usize)
+        .and_then(|(_, _, pane)| pane.as_deref());
     }
 
-    if unique {
-      let mut previous: HashMap<&str, String> = HashMap::new();
+    self.source(mat).map(|pane| pane.id.as_str())
+  }
+
+  /// The pane holding the start of the match, the rightmost one as they are composed.
+  fn source(&self, mat: &Match) -> Option<&Pane> {
+    let (x, y) = (mat.x as usize, mat.y as usize);
+
+    self
+      .panes
+      .iter()
+      .filter(|pane| pane.x <= x && pane.y <= y && y < pane.y + pane.lines.len())
+      .max_by_key(|pane| pane.x)
+  }
 
-      for mat in &mut matches {
-        if let Some(previous_hint) = previous.get(mat.text) {
-          mat.hint = Some(previous_hint.clone());
-        } else if let Some(hint) = hints.pop() {
-          mat.hint = Some(hint.to_string().clone());
-          previous.insert(mat.text, hint.to_string().clone());
+  /// Groups the lines of every pane into haystacks, wrapped lines along the next one.
+  fn haystacks(&self) -> Vec<Haystack<'_>> {
+    let mut haystacks: Vec<Haystack> = Vec::new();
+    let areas = if self.panes.is_empty() {
+      vec![(0, 0, &self.lines)]
+    } else {
+      self
+        .panes
+        .iter()
+        .map(|pane| (pane.x, pane.y, &pane.lines))
+        .collect()
+    };
+
+    for (x, y, lines) in areas {
+      let mut continued = false;
+
+      for (index, line) in lines.iter().enumerate() {
+        match haystacks.last_mut() {
+          Some(haystack) if continued => {
+            haystack.text.push_str(&line.text);
+            haystack.rows.push((y + index, line));
+          }
+          _ => haystacks.push(Haystack {
+            text: line.text.clone(),
+            x: x,
+            rows: vec![(y + index, line)],
+          }),
         }
+
+        continued = line.wrapped;
       }
-    } else {
-      for mat in &mut matches {
-        if let Some(hint) = hints.pop() {
-          mat.hint = Some(hint.to_string().clone());
+    }
+
+    haystacks
+  }
+
+  /// Finds every match in the lines, with their hints.
+  pub fn matches(&self, reverse: bool, unique: bool) -> Vec<Match> {
+    self.hints(self.find(), reverse, unique)
+  }
+
+  /// Finds every match in the lines, without hints.
+  pub fn find(&self) -> Vec<Match> {
+    if let Some(listed) = &self.listed {
+      return listed.iter().map(|(mat, _, _)| mat.clone()).collect();
+    }
+
+    let mut matches = Vec::new();
+
+    for haystack in self.haystacks().iter() {
+      for (name, range) in self.matcher.find(&haystack.text) {
+        let segments = haystack.segments(range.start, range.end);
+
+        if let Some(&Segment { x, y, .. }) = segments.first() {
+          matches.push(Match {
+            x: x,
+            y: y,
+            pattern: name.to_string(),
+            text: haystack.text[range].to_string(),
+            hint: None,
+            segments: segments,
+          });
         }
       }
     }
 
-    if reverse {
-      matches.reverse();
+    // Panes come one after the other, but hints follow the screen
+    if !self.panes.is_empty() {
+      matches.sort_by_key(|mat| (mat.y, mat.x));
+    }
+
+    matches
+  }
+
+  /// Assigns hints to the given matches, the shortest ones first: from the top, from
+  /// the bottom in reverse, or from the closest to the cursor if it's set.
+  pub fn hints(&self, mut matches: Vec<Match>, reverse: bool, unique: bool) -> Vec<Match> {
+    let mut hints = self.alphabet.hints(matches.len()).into_iter();
+    let mut order = (0..matches.len()).collect::<Vec<_>>();
+
+    match self.cursor {
+      Some(cursor) => order.sort_by_key(|index| distance(&matches[*index], cursor)),
+      None if reverse => order.reverse(),
+      None => {}
+    }
+
+    let mut previous: HashMap<String, String> = HashMap::new();
+
+    for index in order {
+      let mat = &mut matches[index];
+
+      if let Some(previous_hint) = previous.get(&mat.text) {
+        mat.hint = Some(previous_hint.clone());
+      } else if let Some(hint) = hints.next() {
+        if unique {
+          previous.insert(mat.text.clone(), hint.clone());
+        }
+
+        mat.hint = Some(hint);
+      }
     }
 
-    return matches;
+    matches
+  }
+}
+
+/// How far the match is from the cursor, by its closest cell. Lines count twice as much
+/// as columns, since cells are about twice as tall as they are wide.
+fn distance(mat: &Match, (x, y): (usize, usize)) -> usize {
+  mat
+    .segments
+    .iter()
+    .map(|segment| {
+      let (start, end) = (segment.x as usize, (segment.x + segment.width) as usize);
+      let columns = if x < start {
+        start - x
+      } else if x >= end {
+        x + 1 - end.max(start + 1)
+      } else {
+        0
+      };
+      let lines = 2 * (segment.y as usize).abs_diff(y);
+
+      lines * lines + columns * columns
+    })
+    .min()
+    .unwrap_or(usize::MAX)
+}
+
+/// Lays the matches out one per line, their text first and then its description, and
+/// moves them there.
+fn listing(entries: &mut [(Match, String)]) -> Vec<Line> {
+  let text_width = entries
+    .iter()
+    .map(|(mat, _)| mat.text.width())
+    .max()
+    .unwrap_or(0);
+  let mut lines = Vec::new();
+
+  for (row, (mat, description)) in entries.iter_mut().enumerate() {
+    let width = mat.text.width();
+
+    lines.extend(capture::parse(&format!(
+      "{}{}  {}",
+      mat.text,
+      " ".repeat(text_width - width),
+      description
+    )));
+
+    mat.x = 0;
+    mat.y = row as i32;
+    mat.segments = vec![Segment {
+      x: 0,
+      y: row as i32,
+      width: width as i32,
+      text: mat.text.clone(),
+    }];
+  }
+
+  lines
+}
+
+/// Splits the lines in rows of the `wrap` width, if any.
+fn rows(lines: Vec<Line>, wrap: Option<usize>) -> Vec<Line> {
+  match wrap {
+    Some(width) => lines.iter().flat_map(|line| line.rows(width)).collect(),
+    None => lines,
+  }
+}
+
+/// Lays the panes out in screen lines, each line holding the ones of every pane in that
+/// row from left to right.
+fn compose(panes: &[Pane]) -> Vec<Line> {
+  let height = panes
+    .iter()
+    .map(|pane| pane.y + pane.lines.len())
+    .max()
+    .unwrap_or(0);
+  let mut lines = vec![Line::default(); height];
+  let mut panes = panes.iter().collect::<Vec<_>>();
+
+  panes.sort_by_key(|pane| pane.x);
+
+  for pane in panes {
+    for (index, line) in pane.lines.iter().enumerate() {
+      let screen = &mut lines[pane.y + index];
+      let offset = screen.text.len();
+
+      screen.text.push_str(&line.text);
+      screen.cells.extend(line.cells.iter().map(|cell| Cell {
+        start: cell.start + offset,
+        end: cell.end + offset,
+        x: cell.x + pane.x,
+        ..cell.clone()
+      }));
+    }
   }
+
+  lines
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::alphabets::get_alphabet;
 
-  fn split(output: &str) -> Vec<&str> {
-    output.split("\n").collect::<Vec<&str>>()
+  fn split(output: &str) -> Vec<Line> {
+    crate::capture::parse(output)
   }
 
   #[test]
   fn match_reverse() {
     let lines = split("lorem 127.0.0.1 lorem 255.255.255.255 lorem 127.0.0.1 lorem");
-    let custom = [].to_vec();
-    let results = State::new(&lines, "abcd", &custom).matches(false, false);
+    let matcher = Matcher::new(&[], &[], &[], &[]).unwrap();
+    let results = State::new(lines, Alphabet::new("abcd"), matcher, None).matches(false, false);
 
     assert_eq!(results.len(), 3);
     assert_eq!(results.first().unwrap().hint.clone().unwrap(), "a");
@@ -199,19 +724,183 @@ mod tests {
   #[test]
   fn match_unique() {
     let lines = split("lorem 127.0.0.1 lorem 255.255.255.255 lorem 127.0.0.1 lorem");
-    let custom = [].to_vec();
-    let results = State::new(&lines, "abcd", &custom).matches(false, true);
+    let matcher = Matcher::new(&[], &[], &[], &[]).unwrap();
+    let results = State::new(lines, Alphabet::new("abcd"), matcher, None).matches(false, true);
 
     assert_eq!(results.len(), 3);
     assert_eq!(results.first().unwrap().hint.clone().unwrap(), "a");
     assert_eq!(results.last().unwrap().hint.clone().unwrap(), "a");
   }
 
+  #[test]
+  fn match_hints_subset() {
+    let lines = split("lorem 127.0.0.1 lorem\nlorem 255.255.255.255 lorem\nlorem 10.0.0.1 lorem");
+    let matcher = Matcher::new(&[], &[], &[], &[]).unwrap();
+    let state = State::new(lines, Alphabet::new("abcd"), matcher, None);
+    let found = state.find();
+
+    assert_eq!(found.len(), 3);
+    assert_eq!(found.iter().filter(|mat| mat.hint.is_some()).count(), 0);
+
+    let page = found.into_iter().filter(|mat| mat.y > 0).collect();
+    let results = state.hints(page, false, false);
+
+    assert_eq!(results.len(), 2);
+    assert_eq!(results.first().unwrap().hint.clone().unwrap(), "a");
+    assert_eq!(results.last().unwrap().text.clone(), "10.0.0.1");
+    assert_eq!(results.last().unwrap().hint.clone().unwrap(), "b");
+  }
+
+  #[test]
+  fn match_window_panes() {
+    let pane = |id: &str, x, y, output| Pane {
+      id: id.to_string(),
+      x: x,
+      y: y,
+      lines: split(output),
+      wrap: None,
+    };
+    let panes = vec![
+      pane("%1", 0, 0, "lorem 127.0.0.1\nipsum /tmp/foo"),
+      pane("%2", 0, 3, "/var/log"),
+      pane("%3", 16, 0, "10.0.0.1\nlorem 10.0.0.2"),
+    ];
+    let matcher = Matcher::new(&[], &[], &[], &[]).unwrap();
+    let state = State::window(panes, Alphabet::new("abcd"), matcher);
+    let results = state.matches(false, false);
+
+    let positions = results
+      .iter()
+      .map(|mat| (mat.x, mat.y, mat.text.as_str(), mat.hint.clone().unwrap()))
+      .collect::<Vec<_>>();
+
+    assert_eq!(
+      positions,
+      vec![
+        (6, 0, "127.0.0.1", "a".to_string()),
+        (16, 0, "10.0.0.1", "b".to_string()),
+        (6, 1, "/tmp/foo", "c".to_string()),
+        (22, 1, "10.0.0.2", "da".to_string()),
+        (0, 3, "/var/log", "db".to_string()),
+      ]
+    );
+    assert_eq!(state.lines.len(), 4);
+    assert_eq!(state.lines[1].text, "ipsum /tmp/foolorem 10.0.0.2");
+    assert_eq!(state.lines[1].column(14), 16);
+    assert_eq!(state.line(&results[3]).unwrap().text, "lorem 10.0.0.2");
+    assert_eq!(state.line(&results[4]).unwrap().text, "/var/log");
+    assert_eq!(state.pane(&results[3]), Some("%3"));
+    assert_eq!(state.pane(&results[4]), Some("%2"));
+  }
+
+  #[test]
+  fn match_session_list() {
+    let panes = vec![
+      (
+        "%1".to_string(),
+        "1.0 vim".to_string(),
+        split("lorem /tmp/foo\nfd70b5695"),
+      ),
+      (
+        "%4".to_string(),
+        "2.0 zsh".to_string(),
+        split("/tmp/foo 127.0.0.1"),
+      ),
+    ];
+    let matcher = Matcher::new(&[], &[], &[], &[]).unwrap();
+    let state = State::list(panes, Alphabet::new("abcd"), matcher);
+    let results = state.matches(false, false);
+
+    let rows = state
+      .lines
+      .iter()
+      .map(|line| line.text.as_str())
+      .collect::<Vec<_>>();
+
+    assert_eq!(
+      rows,
+      vec![
+        "/tmp/foo   path  1.0 vim",
+        "fd70b5695  sha   1.0 vim",
+        "127.0.0.1  ip    2.0 zsh",
+      ]
+    );
+    assert_eq!(results.len(), 3);
+    assert_eq!((results[2].x, results[2].y), (0, 2));
+    assert_eq!(results[2].hint.clone().unwrap(), "c");
+    assert_eq!(state.line(&results[0]).unwrap().text, "lorem /tmp/foo");
+    assert_eq!(state.line(&results[2]).unwrap().text, "/tmp/foo 127.0.0.1");
+    assert_eq!(state.pane(&results[0]), Some("%1"));
+    assert_eq!(state.pane(&results[2]), Some("%4"));
+    assert!(state.is_list());
+  }
+
+  #[test]
+  fn match_choices() {
+    let choices = vec![
+      ("%1".to_string(), "1.0 vim".to_string()),
+      ("%12".to_string(), "2.1 zsh".to_string()),
+    ];
+    let state = State::choices("pane", choices, Alphabet::new("abcd"));
+    let results = state.matches(false, false);
+
+    assert_eq!(state.lines[0].text, "%1   1.0 vim");
+    assert_eq!(state.lines[1].text, "%12  2.1 zsh");
+    assert_eq!(results.len(), 2);
+    assert_eq!(state.pane(&results[0]), None);
+    assert_eq!(results[1].pattern, "pane");
+    assert_eq!(results[1].text, "%12");
+    assert_eq!((results[1].x, results[1].y), (0, 1));
+    assert_eq!(results[1].hint.clone().unwrap(), "b");
+    assert_eq!(state.line(&results[1]).unwrap().text, "%12  2.1 zsh");
+  }
+
+  #[test]
+  fn match_cursor_distance() {
+    let lines = split("127.0.0.1 lorem 10.0.0.1\nlorem\nlorem 10.0.0.2 lorem 10.0.0.3\n10.0.0.4");
+    let matcher = Matcher::new(&[], &[], &[], &[]).unwrap();
+    let mut state = State::new(lines, Alphabet::new("abcd"), matcher, None);
+
+    state.set_cursor(24, 2);
+
+    let results = state.matches(false, false);
+    let hints = results
+      .iter()
+      .map(|mat| (mat.text.as_str(), mat.hint.as_deref().unwrap()))
+      .collect::<Vec<_>>();
+
+    assert_eq!(
+      hints,
+      vec![
+        ("127.0.0.1", "da"),
+        ("10.0.0.1", "b"),
+        ("10.0.0.2", "c"),
+        ("10.0.0.3", "a"),
+        ("10.0.0.4", "db"),
+      ]
+    );
+  }
+
+  #[test]
+  fn match_cursor_ignores_reverse() {
+    let lines = split("10.0.0.1 10.0.0.2\nlorem\nlorem\n10.0.0.1");
+    let matcher = Matcher::new(&[], &[], &[], &[]).unwrap();
+    let mut state = State::new(lines, Alphabet::new("abcd"), matcher, None);
+
+    state.set_cursor(0, 0);
+
+    let results = state.matches(true, true);
+
+    assert_eq!(results[0].hint.clone().unwrap(), "a");
+    assert_eq!(results[1].hint.clone().unwrap(), "b");
+    assert_eq!(results[2].hint.clone().unwrap(), "a");
+  }
+
   #[test]
   fn match_bash() {
     let lines = split("path: [32m/var/log/nginx.log[m\npath: [32mtest/log/nginx.log:32[m");
-    let custom = [].to_vec();
-    let results = State::new(&lines, "abcd", &custom).matches(false, false);
+    let matcher = Matcher::new(&[], &[], &[], &[]).unwrap();
+    let results = State::new(lines, Alphabet::new("abcd"), matcher, None).matches(false, false);
 
     assert_eq!(results.len(), 2);
     assert_eq!(results.first().unwrap().text, "/var/log/nginx.log");
@@ -223,8 +912,8 @@ mod tests {
     let lines = split(
       "Lorem /tmp/foo/bar_lol, lorem\n Lorem /var/log/boot-strap.log lorem ../log/kern.log lorem",
     );
-    let custom = [].to_vec();
-    let results = State::new(&lines, "abcd", &custom).matches(false, false);
+    let matcher = Matcher::new(&[], &[], &[], &[]).unwrap();
+    let results = State::new(lines, Alphabet::new("abcd"), matcher, None).matches(false, false);
 
     assert_eq!(results.len(), 3);
     assert_eq!(results.get(0).unwrap().text.clone(), "/tmp/foo/bar_lol");
@@ -238,8 +927,8 @@ mod tests {
   #[test]
   fn match_uids() {
     let lines = split("Lorem ipsum 123e4567-e89b-12d3-a456-426655440000 lorem\n Lorem lorem lorem");
-    let custom = [].to_vec();
-    let results = State::new(&lines, "abcd", &custom).matches(false, false);
+    let matcher = Matcher::new(&[], &[], &[], &[]).unwrap();
+    let results = State::new(lines, Alphabet::new("abcd"), matcher, None).matches(false, false);
 
     assert_eq!(results.len(), 1);
   }
@@ -247,8 +936,8 @@ mod tests {
   #[test]
   fn match_shas() {
     let lines = split("Lorem fd70b5695 5246ddf f924213 lorem\n Lorem 973113963b491874ab2e372ee60d4b4cb75f717c lorem");
-    let custom = [].to_vec();
-    let results = State::new(&lines, "abcd", &custom).matches(false, false);
+    let matcher = Matcher::new(&[], &[], &[], &[]).unwrap();
+    let results = State::new(lines, Alphabet::new("abcd"), matcher, None).matches(false, false);
 
     assert_eq!(results.len(), 4);
     assert_eq!(results.get(0).unwrap().text.clone(), "fd70b5695");
@@ -263,8 +952,8 @@ mod tests {
   #[test]
   fn match_ips() {
     let lines = split("Lorem ipsum 127.0.0.1 lorem\n Lorem 255.255.10.255 lorem 127.0.0.1 lorem");
-    let custom = [].to_vec();
-    let results = State::new(&lines, "abcd", &custom).matches(false, false);
+    let matcher = Matcher::new(&[], &[], &[], &[]).unwrap();
+    let results = State::new(lines, Alphabet::new("abcd"), matcher, None).matches(false, false);
 
     assert_eq!(results.len(), 3);
     assert_eq!(results.get(0).unwrap().text.clone(), "127.0.0.1");
@@ -276,8 +965,8 @@ mod tests {
   fn match_markdown_urls() {
     let lines =
       split("Lorem ipsum [link](https://github.io?foo=bar) ![](http://cdn.com/img.jpg) lorem");
-    let custom = [].to_vec();
-    let results = State::new(&lines, "abcd", &custom).matches(false, false);
+    let matcher = Matcher::new(&[], &[], &[], &[]).unwrap();
+    let results = State::new(lines, Alphabet::new("abcd"), matcher, None).matches(false, false);
 
     assert_eq!(results.len(), 2);
     assert_eq!(results.get(0).unwrap().pattern.clone(), "markdown_url");
@@ -295,8 +984,8 @@ mod tests {
   #[test]
   fn match_urls() {
     let lines = split("Lorem ipsum https://www.rust-lang.org/tools lorem\n Lorem ipsumhttps://crates.io lorem https://github.io?foo=bar lorem ssh://github.io");
-    let custom = [].to_vec();
-    let results = State::new(&lines, "abcd", &custom).matches(false, false);
+    let matcher = Matcher::new(&[], &[], &[], &[]).unwrap();
+    let results = State::new(lines, Alphabet::new("abcd"), matcher, None).matches(false, false);
 
     assert_eq!(results.len(), 4);
     assert_eq!(
@@ -318,8 +1007,8 @@ mod tests {
   #[test]
   fn match_addresses() {
     let lines = split("Lorem 0xfd70b5695 0x5246ddf lorem\n Lorem 0x973113tlorem");
-    let custom = [].to_vec();
-    let results = State::new(&lines, "abcd", &custom).matches(false, false);
+    let matcher = Matcher::new(&[], &[], &[], &[]).unwrap();
+    let results = State::new(lines, Alphabet::new("abcd"), matcher, None).matches(false, false);
 
     assert_eq!(results.len(), 3);
     assert_eq!(results.get(0).unwrap().text.clone(), "0xfd70b5695");
@@ -330,8 +1019,8 @@ mod tests {
   #[test]
   fn match_hex_colors() {
     let lines = split("Lorem #fd7b56 lorem #FF00FF\n Lorem #00fF05 lorem #abcd00 lorem #afRR00");
-    let custom = [].to_vec();
-    let results = State::new(&lines, "abcd", &custom).matches(false, false);
+    let matcher = Matcher::new(&[], &[], &[], &[]).unwrap();
+    let results = State::new(lines, Alphabet::new("abcd"), matcher, None).matches(false, false);
 
     assert_eq!(results.len(), 4);
     assert_eq!(results.get(0).unwrap().text.clone(), "#fd7b56");
@@ -343,8 +1032,8 @@ mod tests {
   #[test]
   fn match_process_port() {
     let lines = split("Lorem 5695 52463 lorem\n Lorem 973113 lorem 99999 lorem 8888 lorem\n   23456 lorem 5432 lorem 23444");
-    let custom = [].to_vec();
-    let results = State::new(&lines, "abcd", &custom).matches(false, false);
+    let matcher = Matcher::new(&[], &[], &[], &[]).unwrap();
+    let results = State::new(lines, Alphabet::new("abcd"), matcher, None).matches(false, false);
 
     assert_eq!(results.len(), 8);
   }
@@ -352,8 +1041,8 @@ mod tests {
   #[test]
   fn match_diff_a() {
     let lines = split("Lorem lorem\n--- a/src/main.rs");
-    let custom = [].to_vec();
-    let results = State::new(&lines, "abcd", &custom).matches(false, false);
+    let matcher = Matcher::new(&[], &[], &[], &[]).unwrap();
+    let results = State::new(lines, Alphabet::new("abcd"), matcher, None).matches(false, false);
 
     assert_eq!(results.len(), 1);
     assert_eq!(results.get(0).unwrap().text.clone(), "src/main.rs");
@@ -362,18 +1051,85 @@ mod tests {
   #[test]
   fn match_diff_b() {
     let lines = split("Lorem lorem\n+++ b/src/main.rs");
-    let custom = [].to_vec();
-    let results = State::new(&lines, "abcd", &custom).matches(false, false);
+    let matcher = Matcher::new(&[], &[], &[], &[]).unwrap();
+    let results = State::new(lines, Alphabet::new("abcd"), matcher, None).matches(false, false);
 
     assert_eq!(results.len(), 1);
     assert_eq!(results.get(0).unwrap().text.clone(), "src/main.rs");
   }
 
+  #[test]
+  fn match_wide_columns() {
+    let lines = split("日本語 /var/log 😀\t127.0.0.1\ne\u{301}e\u{301} 5246ddf");
+    let matcher = Matcher::new(&[], &[], &[], &[]).unwrap();
+    let results = State::new(lines, Alphabet::new("abcd"), matcher, None).matches(false, false);
+
+    assert_eq!(results.len(), 3);
+    assert_eq!(results.get(0).unwrap().text.clone(), "/var/log");
+    assert_eq!(results.get(0).unwrap().x, 7);
+    assert_eq!(results.get(0).unwrap().segments[0].width, 8);
+    assert_eq!(results.get(1).unwrap().text.clone(), "127.0.0.1");
+    assert_eq!(results.get(1).unwrap().x, 24);
+    assert_eq!(results.get(2).unwrap().text.clone(), "5246ddf");
+    assert_eq!(results.get(2).unwrap().x, 3);
+  }
+
+  #[test]
+  fn match_multiline() {
+    let lines = split("Lorem https://github.com/fcsonline/tmux-thumbs lorem\nipsum /var/log");
+    let matcher = Matcher::new(&[], &[], &[], &[]).unwrap();
+    let results = State::new(lines, Alphabet::new("abcd"), matcher, Some(31)).matches(false, false);
+
+    assert_eq!(results.len(), 2);
+    assert_eq!(
+      results.get(0).unwrap().text.clone(),
+      "https://github.com/fcsonline/tmux-thumbs"
+    );
+    assert_eq!(results.get(0).unwrap().segments.len(), 2);
+    assert_eq!(results.get(0).unwrap().segments[0].x, 6);
+    assert_eq!(results.get(0).unwrap().segments[0].y, 0);
+    assert_eq!(
+      results.get(0).unwrap().segments[0].text,
+      "https://github.com/fcsonl"
+    );
+    assert_eq!(results.get(0).unwrap().segments[1].x, 0);
+    assert_eq!(results.get(0).unwrap().segments[1].y, 1);
+    assert_eq!(results.get(0).unwrap().segments[1].text, "ine/tmux-thumbs");
+    assert_eq!(results.get(1).unwrap().text.clone(), "/var/log");
+    assert_eq!(results.get(1).unwrap().y, 2);
+  }
+
+  #[test]
+  fn match_multiline_full_lines() {
+    let lines = split(&format!("{:075} /tmp\nfoo bar", 0));
+    let matcher = Matcher::new(&[], &[], &["number", "sha"], &[]).unwrap();
+    let results = State::new(lines, Alphabet::new("abcd"), matcher, Some(80)).matches(false, false);
+
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].text, "/tmp");
+    assert_eq!(results[0].segments.len(), 1);
+  }
+
+  #[test]
+  fn match_multiline_short_lines() {
+    let lines = split("Lorem https://github.com/fcsonl\nine/tmux-thumbs lorem");
+    let matcher = Matcher::new(&[], &[], &[], &[]).unwrap();
+    let results = State::new(lines, Alphabet::new("abcd"), matcher, Some(80)).matches(false, false);
+
+    assert_eq!(results.len(), 2);
+    assert_eq!(
+      results.get(0).unwrap().text.clone(),
+      "https://github.com/fcsonl"
+    );
+    assert_eq!(results.get(1).unwrap().text.clone(), "ine/tmux-thumbs");
+  }
+
   #[test]
   fn priority() {
     let lines = split("Lorem [link](http://foo.bar) ipsum CUSTOM-52463 lorem ISSUE-123 lorem\nLorem /var/fd70b569/9999.log 52463 lorem\n Lorem 973113 lorem 123e4567-e89b-12d3-a456-426655440000 lorem 8888 lorem\n  https://crates.io/23456/fd70b569 lorem");
     let custom = ["CUSTOM-[0-9]{4,}", "ISSUE-[0-9]{3}"].to_vec();
-    let results = State::new(&lines, "abcd", &custom).matches(false, false);
+    let matcher = Matcher::new(&custom, &[], &[], &[]).unwrap();
+    let results = State::new(lines, Alphabet::new("abcd"), matcher, None).matches(false, false);
 
     assert_eq!(results.len(), 9);
     assert_eq!(results.get(0).unwrap().text.clone(), "http://foo.bar");
@@ -395,4 +1151,130 @@ mod tests {
       "https://crates.io/23456/fd70b569"
     );
   }
+
+  #[test]
+  fn match_custom_named_groups() {
+    let lines = split("Lorem ISSUE-123 lorem\nLorem commit 5246ddf lorem");
+    let custom = [
+      "(?P<issue>ISSUE-(?P<match>[0-9]+))",
+      "commit (?P<match>[0-9a-f]{7})",
+    ]
+    .to_vec();
+    let matcher = Matcher::new(&custom, &[], &[], &[]).unwrap();
+    let results = State::new(lines, Alphabet::new("abcd"), matcher, None).matches(false, false);
+
+    assert_eq!(results.len(), 2);
+    assert_eq!(results.get(0).unwrap().text.clone(), "123");
+    assert_eq!(results.get(0).unwrap().pattern.clone(), "issue");
+    assert_eq!(results.get(0).unwrap().x, 12);
+    assert_eq!(results.get(1).unwrap().text.clone(), "5246ddf");
+    assert_eq!(results.get(1).unwrap().pattern.clone(), "custom");
+  }
+
+  #[test]
+  fn match_named_custom() {
+    let lines = split("Lorem TICKET-123 lorem");
+    let custom = [(Some("ticket"), r"TICKET-(\d+)")];
+    let matcher = Matcher::named(&custom, &[], &[], &[]).unwrap();
+    let results = State::new(lines, Alphabet::new("abcd"), matcher, None).matches(false, false);
+
+    assert_eq!(results.len(), 1);
+    assert_eq!(results.get(0).unwrap().pattern, "ticket");
+    assert_eq!(results.get(0).unwrap().text, "123");
+  }
+
+  #[test]
+  fn match_disabled_patterns() {
+    let lines = split("Lorem 5695 fd70b5695 lorem 127.0.0.1");
+    let matcher = Matcher::new(&[], &[], &["number", "sha"], &[]).unwrap();
+    let results = State::new(lines, Alphabet::new("abcd"), matcher, None).matches(false, false);
+
+    assert_eq!(results.len(), 1);
+    assert_eq!(results.get(0).unwrap().text.clone(), "127.0.0.1");
+  }
+
+  #[test]
+  fn match_priority_order() {
+    let lines = split("Lorem 5246312 lorem");
+    let matcher = Matcher::new(&[], &[], &[], &[]).unwrap();
+    let results = State::new(lines, Alphabet::new("abcd"), matcher, None).matches(false, false);
+
+    assert_eq!(results.len(), 1);
+    assert_eq!(results.get(0).unwrap().pattern.clone(), "sha");
+
+    let matcher = Matcher::new(&[], &[], &[], &["number"]).unwrap();
+    let results = State::new(
+      split("Lorem 5246312 lorem"),
+      Alphabet::new("abcd"),
+      matcher,
+      None,
+    )
+    .matches(false, false);
+
+    assert_eq!(results.len(), 1);
+    assert_eq!(results.get(0).unwrap().pattern.clone(), "number");
+  }
+
+  #[test]
+  fn match_exclude_patterns() {
+    let lines = split("Lorem PID-5695 lorem 8888");
+    let matcher = Matcher::new(&[], &["PID-[0-9]+"], &[], &[]).unwrap();
+    let results = State::new(lines, Alphabet::new("abcd"), matcher, None).matches(false, false);
+
+    assert_eq!(results.len(), 1);
+    assert_eq!(results.get(0).unwrap().text.clone(), "8888");
+  }
+
+  #[test]
+  fn match_unknown_pattern() {
+    assert!(Matcher::new(&[], &[], &["foo"], &[]).is_err());
+    assert!(Matcher::new(&["[a-"], &[], &[], &[]).is_err());
+  }
+
+  #[test]
+  fn match_empty_custom() {
+    let lines = split("Lorem 127.0.0.1 lorem");
+    let custom = ["x*"].to_vec();
+    let matcher = Matcher::new(&custom, &[], &[], &[]).unwrap();
+    let results = State::new(lines, Alphabet::new("abcd"), matcher, None).matches(false, false);
+
+    assert_eq!(results.len(), 1);
+    assert_eq!(results.get(0).unwrap().text.clone(), "127.0.0.1");
+  }
+
+  #[test]
+  #[ignore]
+  fn bench_large_capture() {
+    // Run it with `cargo test --release -- --ignored --nocapture bench`
+    let samples = [
+      include_str!("../samples/test1"),
+      include_str!("../samples/test2"),
+      include_str!("../samples/test3"),
+      include_str!("../samples/test4"),
+      include_str!("../samples/test5"),
+    ];
+    let corpus = samples
+      .iter()
+      .flat_map(|sample| sample.lines())
+      .cycle()
+      .take(10_000)
+      .collect::<Vec<&str>>()
+      .join("\n");
+    let custom = ["CUSTOM-[0-9]{4,}", "ISSUE-[0-9]{3}"].to_vec();
+
+    let start = std::time::Instant::now();
+    let lines = split(&corpus);
+    let matcher = Matcher::new(&custom, &[], &[], &[]).unwrap();
+    let state = State::new(lines, get_alphabet("qwerty").unwrap(), matcher, None);
+    let results = state.matches(false, false);
+
+    println!(
+      "{} matches in {} lines: {:?}",
+      results.len(),
+      state.lines.len(),
+      start.elapsed()
+    );
+
+    assert!(results.len() > 0);
+  }
 }
@@ -0,0 +1,256 @@
+use super::error::Error;
+use std::collections::HashMap;
+
+/// Values available to the command templates, by placeholder name. `{}` is a shorthand
+/// for `{text}`.
+pub type Placeholders<'a> = HashMap<&'a str, String>;
+
+/// Turns a command template into the arguments to run. The template is split in words
+/// like a shell would, and then each placeholder is replaced by its value, so values
+/// with spaces or quotes always end up in a single argument. With `shell`, the whole
+/// template is run by `sh -c` instead and the values are quoted for it.
+pub fn render(
+  command: &str,
+  shell: bool,
+  placeholders: &Placeholders,
+) -> Result<Vec<String>, Error> {
+  if shell {
+    let script = expand(command, &|name| {
+      placeholders.get(name).map(|value| quote(value))
+    });
+
+    return Ok(vec!["sh".to_string(), "-c".to_string(), script]);
+  }
+
+  let words = split(command)?;
+
+  if words.is_empty() {
+    return Err(Error::InvalidCommand(command.to_string()));
+  }
+
+  Ok(
+    words
+      .iter()
+      .map(|word| expand(word, &|name| placeholders.get(name).cloned()))
+      .collect(),
+  )
+}
+
+/// Parses a `name=command` action, run instead of the pick command for the matches of
+/// the pattern with that name.
+pub fn action(action: &str) -> Result<(&str, &str), Error> {
+  match action.find('=') {
+    Some(index) if index > 0 => Ok((&action[..index], &action[index + 1..])),
+    _ => Err(Error::InvalidAction(action.to_string())),
+  }
+}
+
+/// Tells if the template uses a placeholder, to skip computing the expensive ones.
+pub fn uses(command: &str, name: &str) -> bool {
+  command.contains(&format!("{{{}}}", name))
+}
+
+/// Splits a command in words, honoring single and double quotes and backslash escapes.
+fn split(command: &str) -> Result<Vec<String>, Error> {
+  let mut words = Vec::new();
+  let mut word: Option<String> = None;
+  let mut chars = command.chars();
+
+  while let Some(c) = chars.next() {
+    match c {
+      c if c.is_whitespace() => {
+        if let Some(word) = word.take() {
+          words.push(word);
+        }
+      }
+      '\'' => {
+        let word = word.get_or_insert_with(String::new);
+
+        loop {
+          match chars.next() {
+            Some('\'') => break,
+            Some(c) => word.push(c),
+            None => return Err(Error::InvalidCommand(command.to_string())),
+          }
+        }
+      }
+      '"' => {
+        let word = word.get_or_insert_with(String::new);
+
+        loop {
+          match chars.next() {
+            Some('"') => break,
+            Some('\\') => match chars.next() {
+              Some(c @ '"') | Some(c @ '\\') | Some(c @ '$') | Some(c @ '`') => word.push(c),
+              Some(c) => {
+                word.push('\\');
+                word.push(c);
+              }
+              None => return Err(Error::InvalidCommand(command.to_string())),
+            },
+            Some(c) => word.push(c),
+            None => return Err(Error::InvalidCommand(command.to_string())),
+          }
+        }
+      }
+      '\\' => match chars.next() {
+        Some(c) => word.get_or_insert_with(String::new).push(c),
+        None => return Err(Error::InvalidCommand(command.to_string())),
+      },
+      c => word.get_or_insert_with(String::new).push(c),
+    }
+  }
+
+  if let Some(word) = word {
+    words.push(word);
+  }
+
+  Ok(words)
+}
+
+/// Replaces the known `{name}` placeholders. Anything else between braces is kept, so
+/// commands like `awk '{print}'` still work.
+fn expand(word: &str, value: &dyn Fn(&str) -> Option<String>) -> String {
+  let mut result = String::new();
+  let mut rest = word;
+
+  while let Some(start) = rest.find('{') {
+    result.push_str(&rest[..start]);
+    rest = &rest[start..];
+
+    let replacement = rest.find('}').and_then(|end| {
+      let name = match &rest[1..end] {
+        "" => "text",
+        name => name,
+      };
+
+      value(name).map(|value| (value, end))
+    });
+
+    match replacement {
+      Some((value, end)) => {
+        result.push_str(&value);
+        rest = &rest[end + 1..];
+      }
+      None => {
+        result.push('{');
+        rest = &rest[1..];
+      }
+    }
+  }
+
+  result.push_str(rest);
+  result
+}
+
+/// Quotes a value for `sh`, wrapping it in single quotes.
+pub fn quote(value: &str) -> String {
+  format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn placeholders() -> Placeholders<'static> {
+    let mut placeholders = Placeholders::new();
+
+    placeholders.insert("text", "it's a \"path\" with spaces".to_string());
+    placeholders.insert("pattern", "path".to_string());
+    placeholders.insert("x", "4".to_string());
+
+    placeholders
+  }
+
+  #[test]
+  fn render_words() {
+    let argv = render("tmux set-buffer {}", false, &placeholders()).unwrap();
+
+    assert_eq!(
+      argv,
+      vec!["tmux", "set-buffer", "it's a \"path\" with spaces"]
+    );
+  }
+
+  #[test]
+  fn render_quoted_words() {
+    let argv = render(
+      r#"echo 'a  b' "{pattern}:{x}" c\ d "e\"f" ''"#,
+      false,
+      &placeholders(),
+    );
+
+    assert_eq!(
+      argv.unwrap(),
+      vec!["echo", "a  b", "path:4", "c d", "e\"f", ""]
+    );
+  }
+
+  #[test]
+  fn render_unknown_placeholders() {
+    let argv = render("awk '{print $1}' {y} {", false, &placeholders()).unwrap();
+
+    assert_eq!(argv, vec!["awk", "{print $1}", "{y}", "{"]);
+  }
+
+  #[test]
+  fn render_shell() {
+    let argv = render("echo {} | wc -c", true, &placeholders()).unwrap();
+
+    assert_eq!(argv[0..2], ["sh", "-c"]);
+    assert_eq!(argv[2], r#"echo 'it'\''s a "path" with spaces' | wc -c"#);
+  }
+
+  #[test]
+  fn render_hostile_text() {
+    let mut placeholders = Placeholders::new();
+
+    placeholders.insert("text", "foo;rm -rf ~ $(id)".to_string());
+    placeholders.insert("pane_cwd", "/tmp".to_string());
+
+    let argv = render(
+      r#"tmux new-window -c {pane_cwd} sh -c '"$EDITOR" "$1"' sh {}"#,
+      false,
+      &placeholders,
+    );
+
+    assert_eq!(
+      argv.unwrap(),
+      vec![
+        "tmux",
+        "new-window",
+        "-c",
+        "/tmp",
+        "sh",
+        "-c",
+        r#""$EDITOR" "$1""#,
+        "sh",
+        "foo;rm -rf ~ $(id)"
+      ]
+    );
+  }
+
+  #[test]
+  fn render_invalid() {
+    assert!(render("echo 'foo", false, &placeholders()).is_err());
+    assert!(render("echo \"foo", false, &placeholders()).is_err());
+    assert!(render("  ", false, &placeholders()).is_err());
+  }
+
+  #[test]
+  fn parse_action() {
+    assert_eq!(action("url=open {}").unwrap(), ("url", "open {}"));
+    assert_eq!(
+      action("sha=git show {} --stat=10").unwrap(),
+      ("sha", "git show {} --stat=10")
+    );
+    assert!(action("open {}").is_err());
+    assert!(action("=open {}").is_err());
+  }
+
+  #[test]
+  fn uses_placeholder() {
+    assert!(uses("cd {pane_cwd}", "pane_cwd"));
+    assert!(!uses("tmux set-buffer {}", "pane_cwd"));
+  }
+}
@@ -0,0 +1,263 @@
+use super::error::Error;
+use std::env;
+use std::process::Command;
+use tmux_thumbs::template;
+
+/// Name of the temporary window holding the thumbs pane.
+pub const WINDOW_NAME: &str = "[thumbs]";
+
+/// First tmux version with borderless popups.
+const POPUP_VERSION: (u32, u32) = (3, 3);
+
+/// Every global `@thumbs-*` option, by name without the prefix, read with a single
+/// tmux call.
+pub fn options() -> Result<Vec<(String, String)>, Error> {
+  let output = tmux(&["show-options", "-g"])?;
+
+  Ok(
+    output
+      .lines()
+      .filter_map(|line| {
+        let (name, value) = line.split_at(line.find(' ')?);
+        let name = name.strip_prefix("@thumbs-")?;
+
+        Some((name.to_string(), unescape(&value[1..])))
+      })
+      .collect(),
+  )
+}
+
+/// Runs tmux-thumbs with the same arguments on top of the current pane, or of its
+/// whole window, so the hints show up over the text they hint. That's a popup of the
+/// same size when tmux supports it, and otherwise a temporary window with its pane
+/// swapped in, or shown instead of the window.
+pub fn launch(window: bool) -> Result<(), Error> {
+  let popup = version(&tmux(&["-V"])?).is_some_and(|version| version >= POPUP_VERSION);
+  let output = tmux(&[
+    "display-message",
+    "-p",
+    "#{pane_id} #{pane_width} #{pane_height} #{window_width} #{window_height}",
+  ])?;
+  let (pane, width, height, window_width, window_height) =
+    match output.split(' ').collect::<Vec<_>>()[..] {
+      [pane, width, height, window_width, window_height] => {
+        (pane, width, height, window_width, window_height)
+      }
+      _ => {
+        return Err(Error::CommandFailed(
+          "tmux display-message".to_string(),
+          output.clone(),
+        ))
+      }
+    };
+  let program = env::current_exe().map_err(|e| Error::Command("tmux-thumbs".to_string(), e))?;
+
+  let mut words = vec![template::quote(&program.to_string_lossy())];
+
+  words.extend(
+    env::args_os()
+      .skip(1)
+      .map(|arg| arg.to_string_lossy().into_owned())
+      .filter(|arg| arg != "--launch")
+      .map(|arg| template::quote(&arg)),
+  );
+  words.push("--tmux-pane".to_string());
+  words.push(template::quote(pane));
+
+  let command = words.join(" ");
+
+  if popup {
+    // The position of the window comes from the one of the pane in it, and `-y` is
+    // where the popup ends
+    let (x, y, width, height) = if window {
+      (
+        "#{e|-:#{popup_pane_left},#{pane_left}}",
+        "#{e|+:#{e|-:#{popup_pane_bottom},#{e|+:#{pane_top},#{pane_height}}},#{window_height}}",
+        window_width,
+        window_height,
+      )
+    } else {
+      ("P", "P", width, height)
+    };
+
+    // The popup exits like tmux-thumbs in it, which already told why it failed
+    display(&[
+      "display-popup",
+      "-B",
+      "-E",
+      "-t",
+      pane,
+      "-x",
+      x,
+      "-y",
+      y,
+      "-w",
+      width,
+      "-h",
+      height,
+      &command,
+    ])?;
+
+    return Ok(());
+  }
+
+  if window {
+    tmux(&["new-window", "-n", WINDOW_NAME, &command])?;
+
+    return Ok(());
+  }
+
+  let thumbs_pane = tmux(&[
+    "new-window",
+    "-P",
+    "-F",
+    "#{pane_id}",
+    "-d",
+    "-n",
+    WINDOW_NAME,
+    &command,
+  ])?;
+
+  tmux(&["swap-pane", "-d", "-s", pane, "-t", &thumbs_pane])?;
+
+  Ok(())
+}
+
+/// Reads the major and minor numbers of `tmux -V`, like `tmux 3.3a` or `tmux next-3.4`.
+/// Builds from master are newer than any release.
+fn version(output: &str) -> Option<(u32, u32)> {
+  let version = output.trim().rsplit([' ', '-']).next()?;
+
+  if version == "master" {
+    return Some((u32::MAX, 0));
+  }
+
+  let mut numbers = version.split('.').map(|number| {
+    number
+      .trim_end_matches(|c: char| c.is_ascii_alphabetic())
+      .parse::<u32>()
+      .ok()
+  });
+
+  Some((numbers.next()??, numbers.next().unwrap_or(Some(0))?))
+}
+
+/// Runs a tmux command and returns its output, without the trailing newline.
+fn tmux(args: &[&str]) -> Result<String, Error> {
+  let output = Command::new("tmux")
+    .args(args)
+    .output()
+    .map_err(|e| Error::Command("tmux".to_string(), e))?;
+
+  if !output.status.success() {
+    let message = String::from_utf8_lossy(&output.stderr);
+
+    return Err(Error::CommandFailed(
+      format!("tmux {}", args[0]),
+      message.trim().to_string(),
+    ));
+  }
+
+  Ok(
+    String::from_utf8_lossy(&output.stdout)
+      .trim_end_matches('\n')
+      .to_string(),
+  )
+}
+
+/// Runs a tmux command showing a program, like `display-popup -E`, which exits with the
+/// status of the program. It only fails when tmux itself complains.
+fn display(args: &[&str]) -> Result<(), Error> {
+  let output = Command::new("tmux")
+    .args(args)
+    .output()
+    .map_err(|e| Error::Command("tmux".to_string(), e))?;
+  let message = String::from_utf8_lossy(&output.stderr);
+
+  if message.trim().is_empty() {
+    Ok(())
+  } else {
+    Err(Error::CommandFailed(
+      format!("tmux {}", args[0]),
+      message.trim().to_string(),
+    ))
+  }
+}
+
+/// Reads a value as printed by `show-options`: quoted when it has spaces or special
+/// characters, and with them escaped like `vis(3)` does.
+fn unescape(value: &str) -> String {
+  let mut bytes = Vec::with_capacity(value.len());
+  let mut quote = None;
+  let mut chars = value.chars().peekable();
+
+  while let Some(c) = chars.next() {
+    match c {
+      '"' | '\'' if quote.is_none() => quote = Some(c),
+      c if Some(c) == quote => quote = None,
+      '\\' => {
+        let escaped = match chars.next() {
+          Some(digit @ '0'..='7') => {
+            let mut byte = digit.to_digit(8).unwrap();
+
+            for _ in 0..2 {
+              match chars.peek().and_then(|digit| digit.to_digit(8)) {
+                Some(digit) => {
+                  byte = byte * 8 + digit;
+                  chars.next();
+                }
+                None => break,
+              }
+            }
+
+            bytes.push(byte as u8);
+            continue;
+          }
+          Some('a') => '\x07',
+          Some('b') => '\x08',
+          Some('f') => '\x0c',
+          Some('n') => '\n',
+          Some('r') => '\r',
+          Some('t') => '\t',
+          Some('v') => '\x0b',
+          Some(c) => c,
+          None => '\\',
+        };
+
+        bytes.extend_from_slice(escaped.encode_utf8(&mut [0; 4]).as_bytes());
+      }
+      c => bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+    }
+  }
+
+  String::from_utf8_lossy(&bytes).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parse_version() {
+    assert_eq!(version("tmux 3.3a\n"), Some((3, 3)));
+    assert_eq!(version("tmux 2.9"), Some((2, 9)));
+    assert_eq!(version("tmux next-3.4"), Some((3, 4)));
+    assert_eq!(version("tmux master"), Some((u32::MAX, 0)));
+    assert_eq!(version("tmux 3.0-rc"), None);
+    assert!(version("tmux 3.2a").unwrap() < POPUP_VERSION);
+  }
+
+  #[test]
+  fn unescape_values() {
+    assert_eq!(unescape("dvorak"), "dvorak");
+    assert_eq!(unescape("''"), "");
+    assert_eq!(unescape("'tmux paste-buffer'"), "tmux paste-buffer");
+    assert_eq!(
+      unescape(r#""tmux set-buffer -- {} && tmux display-message \"Copied \$x\"""#),
+      r#"tmux set-buffer -- {} && tmux display-message "Copied $x""#
+    );
+    assert_eq!(unescape(r#""[a-z]+\\d 'x'""#), r"[a-z]+\d 'x'");
+    assert_eq!(unescape(r"\~"), "~");
+    assert_eq!(unescape(r"a\tb\012caf\303\251"), "a\tb\ncafé");
+  }
+}
@@ -1,15 +1,59 @@
 use super::*;
 use rustbox::Key;
 use rustbox::{Color, OutputMode, RustBox};
-use std::char;
 use std::default::Default;
 
+/// How the user picked a match.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Pick {
+  Copy,
+  Paste,
+  Prefix(usize),
+}
+
+/// A key typed before the hint to run another command with the match. Either a
+/// character, or a control one like `C-o`.
+pub struct Prefix<'a> {
+  pub name: &'a str,
+  pub key: Key,
+  pub command: &'a str,
+}
+
+impl<'a> Prefix<'a> {
+  pub fn new(name: &'a str, command: &'a str) -> Result<Prefix<'a>, error::Error> {
+    let mut chars = name.chars();
+
+    let key = match (chars.next(), chars.next(), chars.next(), chars.next()) {
+      (Some(ch), None, _, _) if !ch.is_whitespace() && !ch.is_control() => Key::Char(ch),
+      // Scrolling uses C-u and C-d, and the terminal sends C-h, C-i and C-m as other keys
+      (Some('C'), Some('-'), Some(ch), None)
+        if ch.is_ascii_lowercase() && !"dhimu".contains(ch) =>
+      {
+        Key::Ctrl(ch)
+      }
+      _ => return Err(error::Error::InvalidKey(name.to_string())),
+    };
+
+    Ok(Prefix {
+      name: name,
+      key: key,
+      command: command,
+    })
+  }
+}
+
 pub struct View<'a> {
-  state: &'a mut state::State<'a>,
+  state: &'a mut state::State,
   skip: usize,
+  scroll: usize,
+  prefixes: &'a [Prefix<'a>],
+  prefix: Option<usize>,
+  multi: bool,
+  chosen: Vec<state::Match>,
   reverse: bool,
   unique: bool,
   position: &'a str,
+  basic: bool,
   select_foreground_color: Color,
   foreground_color: Color,
   background_color: Color,
@@ -19,10 +63,12 @@ pub struct View<'a> {
 
 impl<'a> View<'a> {
   pub fn new(
-    state: &'a mut state::State<'a>,
+    state: &'a mut state::State,
     reverse: bool,
     unique: bool,
     position: &'a str,
+    prefixes: &'a [Prefix<'a>],
+    multi: bool,
     select_foreground_color: Color,
     foreground_color: Color,
     background_color: Color,
@@ -32,9 +78,15 @@ impl<'a> View<'a> {
     View {
       state: state,
       skip: 0,
+      scroll: 0,
+      prefixes: prefixes,
+      prefix: None,
+      multi: multi,
+      chosen: Vec::new(),
       reverse: reverse,
       unique: unique,
       position: position,
+      basic: false,
       select_foreground_color: select_foreground_color,
       foreground_color: foreground_color,
       background_color: background_color,
@@ -55,135 +107,407 @@ impl<'a> View<'a> {
     }
   }
 
-  pub fn present(&mut self) -> Option<(String, bool)> {
+  /// Picks the overlay color, or the captured one when it is the default color. Only
+  /// the terminal colors keep the default ones of the terminal.
+  fn overlay(&self, color: Color, style: &capture::Style, foreground: bool) -> Color {
+    let (captured, fallback) = if foreground {
+      (style.foreground, Color::White)
+    } else {
+      (style.background, Color::Black)
+    };
+
+    match color {
+      Color::Default if self.basic => colors::get_basic_color(captured).0,
+      Color::Default => colors::get_capture_color(captured, fallback),
+      color => color,
+    }
+  }
+
+  /// Draws a captured cell with its original style, and the given colors on top.
+  fn print_cell(
+    &self,
+    rustbox: &RustBox,
+    y: usize,
+    line: &capture::Line,
+    cell: &capture::Cell,
+    foreground: Color,
+    background: Color,
+  ) {
+    let mut attributes = rustbox::RB_NORMAL;
+    let bright = self.basic
+      && foreground == Color::Default
+      && colors::get_basic_color(cell.style.foreground).1;
+
+    if cell.style.bold || bright {
+      attributes.insert(rustbox::RB_BOLD);
+    }
+    if cell.style.underline {
+      attributes.insert(rustbox::RB_UNDERLINE);
+    }
+    if cell.style.reverse {
+      attributes.insert(rustbox::RB_REVERSE);
+    }
+
+    // Termbox holds a single character per cell, so combining marks are dropped and tabs
+    // are drawn as blanks
+    let ch = match line.cell_text(cell).chars().next() {
+      Some('\t') | None => ' ',
+      Some(ch) => ch,
+    };
+
+    rustbox.print_char(
+      cell.x,
+      y,
+      attributes,
+      self.overlay(foreground, &cell.style, true),
+      self.overlay(background, &cell.style, false),
+      ch,
+    );
+  }
+
+  /// Matches starting in the visible lines, with their hints assigned.
+  fn page(&self, found: &[state::Match], height: usize) -> Vec<state::Match> {
+    let rows = self.scroll..self.scroll + height;
+    let visible = found
+      .iter()
+      .filter(|mat| rows.contains(&(mat.y as usize)))
+      .cloned()
+      .collect();
+
+    self.state.hints(visible, self.reverse, self.unique)
+  }
+
+  /// Lists the prefixes in the last line, the chosen one highlighted.
+  fn print_prefixes(&self, rustbox: &RustBox) {
+    let mut x = 0;
+
+    for (index, prefix) in self.prefixes.iter().enumerate() {
+      let label = format!(" {} {} ", prefix.name, prefix.command);
+      let (foreground, background) = if self.prefix == Some(index) {
+        (self.hint_foreground_color, self.hint_background_color)
+      } else {
+        (Color::White, Color::Black)
+      };
+      let style = capture::Style::default();
+
+      rustbox.print(
+        x,
+        rustbox.height().saturating_sub(1),
+        rustbox::RB_NORMAL,
+        self.overlay(foreground, &style, true),
+        self.overlay(background, &style, false),
+        label.as_str(),
+      );
+
+      x += label.chars().count() + 1;
+    }
+  }
+
+  fn is_chosen(&self, mat: &state::Match) -> bool {
+    self
+      .chosen
+      .iter()
+      .any(|chosen| (chosen.x, chosen.y) == (mat.x, mat.y))
+  }
+
+  /// Adds the match to the chosen ones in multi-select mode, or removes it if it was.
+  fn toggle(&mut self, mat: &state::Match) {
+    match self
+      .chosen
+      .iter()
+      .position(|chosen| (chosen.x, chosen.y) == (mat.x, mat.y))
+    {
+      Some(index) => {
+        self.chosen.remove(index);
+      }
+      None => self.chosen.push(mat.clone()),
+    }
+  }
+
+  /// The chosen matches in the order they show up, or the selected one if there are none.
+  fn confirm(&self, selected: Option<&state::Match>) -> Option<Vec<state::Match>> {
+    if self.chosen.is_empty() {
+      return selected.map(|mat| vec![mat.clone()]);
+    }
+
+    let mut chosen = self.chosen.clone();
+    chosen.sort_by_key(|mat| (mat.y, mat.x));
+
+    Some(chosen)
+  }
+
+  fn pick(&self, paste: bool) -> Pick {
+    match self.prefix {
+      Some(index) => Pick::Prefix(index),
+      None if paste => Pick::Paste,
+      None => Pick::Copy,
+    }
+  }
+
+  /// Lets the user pick a match, or several in multi-select mode where hints toggle them
+  /// and Enter confirms.
+  pub fn present(&mut self) -> Result<Option<(Vec<state::Match>, Pick)>, error::Error> {
+    let found = self.state.find();
+
+    if found.is_empty() {
+      return Err(error::Error::NoMatches);
+    }
+
     let mut rustbox = match RustBox::init(Default::default()) {
       Result::Ok(v) => v,
-      Result::Err(e) => panic!("{}", e),
+      Result::Err(e) => return Err(error::Error::Terminal(e.to_string())),
     };
 
-    rustbox.set_output_mode(OutputMode::EightBit);
-
-    let mut typed_hint: String = "".to_owned();
-    let matches = self.state.matches(self.reverse, self.unique);
-    let longest_hint = matches
+    // The terminal colors are enough for most panes, and keep its default colors
+    self.basic = self
+      .state
+      .lines
       .iter()
-      .filter_map(|m| m.hint.clone())
-      .max_by(|x, y| x.len().cmp(&y.len()))
-      .unwrap()
-      .clone();
-    let mut selected;
+      .flat_map(|line| line.cells.iter())
+      .all(|cell| {
+        colors::is_basic_color(cell.style.foreground, true)
+          && colors::is_basic_color(cell.style.background, false)
+      });
 
-    self.skip = if self.reverse { matches.len() - 1 } else { 0 };
+    rustbox.set_output_mode(if self.basic {
+      OutputMode::Normal
+    } else {
+      OutputMode::EightBit
+    });
 
-    loop {
-      rustbox.clear();
-      rustbox.present();
+    // The prefixes take the last line
+    let height = if self.prefixes.is_empty() {
+      rustbox.height()
+    } else {
+      rustbox.height().saturating_sub(1)
+    };
+    let last_scroll = self.state.lines.len().saturating_sub(height);
 
-      for (index, line) in self.state.lines.iter().enumerate() {
-        let clean = line.trim_end_matches(|c: char| c.is_whitespace());
+    // The last page is the visible screen when there is history, while lists of matches
+    // or panes read from the top
+    self.scroll = if self.state.is_list() { 0 } else { last_scroll };
 
-        if clean.len() > 0 {
-          rustbox.print(
-            0,
-            index,
-            rustbox::RB_NORMAL,
-            Color::White,
-            Color::Black,
-            line,
-          );
+    'page: loop {
+      let mut typed_hint: String = "".to_owned();
+      let matches = self.page(&found, height);
+      let longest_hint = matches
+        .iter()
+        .filter_map(|m| m.hint.as_ref().map(|hint| hint.len()))
+        .max()
+        .unwrap_or(0);
+      let mut selected;
+
+      self.skip = if self.reverse {
+        matches.len().saturating_sub(1)
+      } else {
+        0
+      };
+
+      loop {
+        rustbox.clear();
+        rustbox.present();
+
+        for (index, line) in self
+          .state
+          .lines
+          .iter()
+          .enumerate()
+          .skip(self.scroll)
+          .take(height)
+        {
+          for cell in line.cells.iter() {
+            self.print_cell(
+              &rustbox,
+              index - self.scroll,
+              line,
+              cell,
+              Color::Default,
+              Color::Default,
+            );
+          }
         }
-      }
 
-      selected = matches.get(self.skip);
-
-      for mat in matches.iter() {
-        let selected_color = if selected == Some(mat) {
-          self.select_foreground_color
-        } else {
-          self.foreground_color
-        };
-
-        // Find long utf sequences and extract it from mat.x
-        let line = &self.state.lines[mat.y as usize];
-        let prefix = &line[0..mat.x as usize];
-        let extra = prefix.len() - prefix.chars().count();
-        let offset = (mat.x as usize) - extra;
-
-        rustbox.print(
-          offset,
-          mat.y as usize,
-          rustbox::RB_NORMAL,
-          selected_color,
-          self.background_color,
-          mat.text,
-        );
-
-        if let Some(ref hint) = mat.hint {
-          let extra_position = if self.position == "left" {
-            0
+        selected = matches.get(self.skip);
+
+        for mat in matches.iter() {
+          let chosen = self.is_chosen(mat);
+          let selected_color = if selected == Some(mat) || chosen {
+            self.select_foreground_color
           } else {
-            mat.text.len() - mat.hint.clone().unwrap().len()
+            self.foreground_color
           };
 
+          for segment in mat.segments.iter() {
+            let y = segment.y as usize;
+
+            if y < self.scroll || y >= self.scroll + height {
+              continue;
+            }
+
+            let line = &self.state.lines[y];
+            let start = segment.x as usize;
+            let end = (segment.x + segment.width) as usize;
+
+            for cell in line.cells.iter() {
+              if cell.x >= start && cell.x < end {
+                self.print_cell(
+                  &rustbox,
+                  y - self.scroll,
+                  line,
+                  cell,
+                  selected_color,
+                  self.background_color,
+                );
+              }
+            }
+          }
+
+          if let Some(ref hint) = mat.hint {
+            let (segment, extra_position) = if self.position == "left" {
+              (mat.segments.first().unwrap(), 0)
+            } else {
+              let last = mat.segments.last().unwrap();
+              (last, (last.width as usize).saturating_sub(hint.len()))
+            };
+
+            let x = segment.x as usize + extra_position;
+            let y = segment.y as usize;
+
+            if y >= self.scroll + height {
+              continue;
+            }
+
+            let line = &self.state.lines[y];
+            let style = line
+              .cells
+              .iter()
+              .find(|cell| cell.x == x)
+              .map_or(capture::Style::default(), |cell| cell.style);
+
+            // Chosen matches have their hint colors swapped
+            let (foreground, background) = if chosen {
+              (self.hint_background_color, self.hint_foreground_color)
+            } else {
+              (self.hint_foreground_color, self.hint_background_color)
+            };
+
+            rustbox.print(
+              x,
+              y - self.scroll,
+              rustbox::RB_BOLD,
+              self.overlay(foreground, &style, true),
+              self.overlay(background, &style, false),
+              hint.as_str(),
+            );
+          }
+        }
+
+        // Same position indicator as tmux copy mode
+        if last_scroll > 0 {
+          let position = format!("[{}/{}]", last_scroll - self.scroll, last_scroll);
+
           rustbox.print(
-            offset + extra_position,
-            mat.y as usize,
-            rustbox::RB_BOLD,
-            self.hint_foreground_color,
-            self.hint_background_color,
-            hint.as_str(),
+            rustbox.width().saturating_sub(position.len()),
+            0,
+            rustbox::RB_REVERSE,
+            Color::White,
+            Color::Black,
+            position.as_str(),
           );
         }
-      }
 
-      rustbox.present();
+        if !self.prefixes.is_empty() {
+          self.print_prefixes(&rustbox);
+        }
 
-      match rustbox.poll_event(false) {
-        Ok(rustbox::Event::KeyEvent(key)) => match key {
-          Key::Esc => {
-            break;
-          }
-          Key::Enter => match matches.iter().enumerate().find(|&h| h.0 == self.skip) {
-            Some(hm) => return Some((hm.1.text.to_string(), false)),
-            _ => panic!("Match not found?"),
-          },
-          Key::Up => {
-            self.prev();
-          }
-          Key::Down => {
-            self.next(matches.len() - 1);
-          }
-          Key::Left => {
-            self.prev();
-          }
-          Key::Right => {
-            self.next(matches.len() - 1);
-          }
-          Key::Char(ch) => {
-            let key = ch.to_string();
-            let lower_key = key.to_lowercase();
+        rustbox.present();
 
-            typed_hint.push_str(lower_key.as_str());
+        match rustbox.poll_event(false) {
+          Ok(rustbox::Event::KeyEvent(key)) => match key {
+            key if typed_hint.is_empty() && self.prefixes.iter().any(|p| p.key == key) => {
+              let index = self.prefixes.iter().position(|p| p.key == key);
 
-            match matches
-              .iter()
-              .find(|mat| mat.hint == Some(typed_hint.clone()))
-            {
-              Some(mat) => return Some((mat.text.to_string(), key != lower_key)),
-              None => {
-                if typed_hint.len() >= longest_hint.len() {
-                  break;
+              self.prefix = if self.prefix == index { None } else { index };
+            }
+            Key::Esc => {
+              break 'page;
+            }
+            Key::Enter => {
+              if let Some(chosen) = self.confirm(matches.get(self.skip)) {
+                return Ok(Some((chosen, self.pick(false))));
+              }
+            }
+            Key::Up => {
+              self.prev();
+            }
+            Key::Down => {
+              self.next(matches.len().saturating_sub(1));
+            }
+            Key::Left => {
+              self.prev();
+            }
+            Key::Right => {
+              self.next(matches.len().saturating_sub(1));
+            }
+            Key::PageUp | Key::Ctrl('u') => {
+              let lines = if key == Key::PageUp {
+                height
+              } else {
+                height / 2
+              };
+
+              if self.scroll > 0 {
+                self.scroll = self.scroll.saturating_sub(lines);
+                continue 'page;
+              }
+            }
+            Key::PageDown | Key::Ctrl('d') => {
+              let lines = if key == Key::PageDown {
+                height
+              } else {
+                height / 2
+              };
+
+              if self.scroll < last_scroll {
+                self.scroll = (self.scroll + lines).min(last_scroll);
+                continue 'page;
+              }
+            }
+            Key::Char(ch) => {
+              let key = ch.to_string();
+              let lower_key = key.to_lowercase();
+
+              typed_hint.push_str(lower_key.as_str());
+
+              match matches
+                .iter()
+                .find(|mat| mat.hint == Some(typed_hint.clone()))
+              {
+                Some(mat) if self.multi => {
+                  self.toggle(mat);
+                  typed_hint.clear();
+                }
+                Some(mat) => return Ok(Some((vec![mat.clone()], self.pick(key != lower_key)))),
+                None => {
+                  if typed_hint.len() >= longest_hint {
+                    // Keep the chosen matches around after a typo
+                    if self.multi {
+                      typed_hint.clear();
+                    } else {
+                      break 'page;
+                    }
+                  }
                 }
               }
             }
-          }
+            _ => {}
+          },
+          Err(e) => return Err(error::Error::Terminal(e.to_string())),
           _ => {}
-        },
-        Err(e) => panic!("{}", e),
-        _ => {}
+        }
       }
     }
 
-    None
+    Ok(None)
   }
 }
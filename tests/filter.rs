@@ -0,0 +1,46 @@
+use std::env;
+use std::io::Write;
+use std::process::{self, Command, Output, Stdio};
+
+fn filter(args: &[&str], input: &str) -> Output {
+  // Keep away from the config file of whoever runs the tests
+  let config = env::temp_dir().join(format!("tmux-thumbs-filter-{}", process::id()));
+  let mut child = Command::new(env!("CARGO_BIN_EXE_tmux-thumbs"))
+    .args(args)
+    .env("XDG_CONFIG_HOME", config)
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .unwrap();
+
+  child
+    .stdin
+    .take()
+    .unwrap()
+    .write_all(input.as_bytes())
+    .unwrap();
+
+  child.wait_with_output().unwrap()
+}
+
+#[test]
+fn filter_without_matches() {
+  let output = filter(&["--input", "-"], "lorem ipsum\n\x1b[31mdolor\x1b[m\n");
+
+  assert_eq!(output.status.code(), Some(2));
+  assert!(output.stdout.is_empty());
+  assert_eq!(
+    String::from_utf8_lossy(&output.stderr),
+    "tmux-thumbs: No matches\n"
+  );
+}
+
+#[test]
+fn filter_missing_file() {
+  let output = filter(&["--input", "/nonexistent/thumbs.log"], "");
+
+  assert_eq!(output.status.code(), Some(1));
+  assert!(String::from_utf8_lossy(&output.stderr)
+    .starts_with("tmux-thumbs: Couldn't read /nonexistent/thumbs.log"));
+}
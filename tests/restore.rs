@@ -0,0 +1,162 @@
+use std::env;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::process::{Child, Command};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Stand-in for tmux 3.3 that logs every call. Both panes live in the `[thumbs]`
+/// window and the capture is empty, or never ends when `FAKE_TMUX_HANG` is set. Killing
+/// the window hangs tmux-thumbs up, and popups exit like it does without matches.
+const FAKE_TMUX: &str = r##"#!/bin/sh
+echo "$@" >> "$FAKE_TMUX_LOG"
+
+case "$1" in
+  -V)
+    echo "tmux 3.3a"
+    ;;
+  display-message)
+    case "$3" in
+      "#{pane_id}"*) echo "%1 80 24 80 24" ;;
+      *) [ "$2" = "-p" ] && echo "[thumbs]" ;;
+    esac
+    ;;
+  display-popup)
+    exit 2
+    ;;
+  kill-window)
+    kill -HUP "$PPID"
+    ;;
+  capture-pane)
+    [ -n "$FAKE_TMUX_HANG" ] && exec sleep 10
+    ;;
+esac
+
+exit 0
+"##;
+
+struct Fake {
+  dir: PathBuf,
+}
+
+impl Fake {
+  fn new(name: &str) -> Fake {
+    let dir = env::temp_dir().join(format!("tmux-thumbs-{}-{}", name, std::process::id()));
+    let tmux = dir.join("tmux");
+
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(&tmux, FAKE_TMUX).unwrap();
+    fs::set_permissions(&tmux, fs::Permissions::from_mode(0o755)).unwrap();
+
+    Fake { dir: dir }
+  }
+
+  fn spawn(&self, args: &[&str], hang: bool) -> Child {
+    let path = format!(
+      "{}:{}",
+      self.dir.display(),
+      env::var("PATH").unwrap_or_default()
+    );
+    let mut command = Command::new(env!("CARGO_BIN_EXE_tmux-thumbs"));
+
+    command
+      .args(args)
+      .env("PATH", path)
+      .env("TMUX_PANE", "%2")
+      .env("XDG_CONFIG_HOME", &self.dir)
+      .env("FAKE_TMUX_LOG", self.dir.join("log"));
+
+    if hang {
+      command.env("FAKE_TMUX_HANG", "1");
+    }
+
+    command.spawn().unwrap()
+  }
+
+  fn log(&self) -> Vec<String> {
+    fs::read_to_string(self.dir.join("log"))
+      .unwrap_or_default()
+      .lines()
+      .map(|line| line.to_string())
+      .collect()
+  }
+
+  fn wait_for(&self, call: &str) {
+    let start = Instant::now();
+
+    while !self.log().iter().any(|line| line.starts_with(call)) {
+      assert!(
+        start.elapsed() < Duration::from_secs(10),
+        "no {} call",
+        call
+      );
+      thread::sleep(Duration::from_millis(20));
+    }
+  }
+}
+
+impl Drop for Fake {
+  fn drop(&mut self) {
+    let _ = fs::remove_dir_all(&self.dir);
+  }
+}
+
+fn assert_restored(log: &[String]) {
+  let swap = log.iter().position(|line| line == "swap-pane -t %1");
+  let kill = log.iter().position(|line| line == "kill-window -t %2");
+
+  assert!(swap.is_some(), "pane not swapped back: {:?}", log);
+  assert!(kill.is_some(), "window not killed: {:?}", log);
+  assert!(swap < kill);
+  assert_eq!(
+    log
+      .iter()
+      .filter(|line| line.starts_with("swap-pane"))
+      .count(),
+    1
+  );
+}
+
+#[test]
+fn restore_without_matches() {
+  let fake = Fake::new("empty");
+  let status = fake.spawn(&["--tmux-pane", "%1"], false).wait().unwrap();
+
+  // Not the hang up of the killed window
+  assert_eq!(status.code(), Some(2));
+  assert_restored(&fake.log());
+}
+
+#[test]
+fn restore_on_signal() {
+  let fake = Fake::new("signal");
+  let mut child = fake.spawn(&["--tmux-pane", "%1"], true);
+
+  fake.wait_for("capture-pane");
+
+  Command::new("kill")
+    .args(&["-TERM", &child.id().to_string()])
+    .status()
+    .unwrap();
+
+  let status = child.wait().unwrap();
+
+  assert_eq!(status.code(), Some(128 + 15));
+  assert_restored(&fake.log());
+}
+
+#[test]
+fn launch_popup_without_matches() {
+  let fake = Fake::new("popup");
+  let status = fake.spawn(&["--launch"], false).wait().unwrap();
+  let log = fake.log();
+
+  // tmux-thumbs in the popup already told there were no matches
+  assert!(status.success(), "launcher failed: {:?}", log);
+  assert!(log.iter().any(|line| line.starts_with("display-popup")));
+  assert!(!log
+    .iter()
+    .any(|line| line.starts_with("display-message tmux-thumbs")));
+}